@@ -5,14 +5,15 @@ use bevy::prelude::*;
 use bevy::prelude::TimerMode::Repeating;
 use rand::prelude::*;
 
-use crate::AnimChannel;
-use crate::cocos2d_anim::{AnimationFaceDir, AnimationMode, Cocos2dAnimator};
+use crate::cocos2d_anim::{AnimationFaceDir, Cocos2dAnimator};
 use crate::cocos2d_anim::anim::Cocos2dAnimAsset;
+use crate::cocos2d_anim::state_machine::Cocos2dAnimStateMachine;
 use crate::game::GameStates;
 use crate::game::GameStates::{Loading, Playing, PrepareLoad, PrepareScene};
 use crate::map::{TmxMap, TmxMapAsset};
 use crate::resource::{ConfigResource, ResourcePath};
-use crate::unit::{get_unit_resources, UnitAnimName, UnitBundle, UnitTeamLeft, UnitTeamRight};
+use crate::resource::action::projectile::PROJECTILE_ANIM_STATE_MACHINE;
+use crate::unit::{get_unit_resources, UNIT_ANIM_STATE_MACHINE, UnitBundle, UnitTeamLeft, UnitTeamRight};
 
 pub struct ClashPlugin;
 
@@ -66,9 +67,6 @@ fn debug_uint_gen(
         Cocos2dAnimator {
             anim_handle: asset_server.load(unit_info.animation_name.anim_path()),
             face_dir: AnimationFaceDir::Right,
-            event_channel: Some(AnimChannel::Unit.into()),
-            new_anim: Some(UnitAnimName::Born.into()),
-            mode: AnimationMode::Once,
             ..default()
         },
     ));
@@ -89,9 +87,6 @@ fn debug_uint_gen(
         Cocos2dAnimator {
             anim_handle: asset_server.load(unit_info.animation_name.anim_path()),
             face_dir: AnimationFaceDir::Left,
-            event_channel: Some(AnimChannel::Unit.into()),
-            new_anim: Some(UnitAnimName::Born.into()),
-            mode: AnimationMode::Once,
             ..default()
         },
     ));
@@ -110,6 +105,8 @@ struct LoadedResource {
 
     map_loaded: bool,
     map_handle: Handle<TmxMapAsset>,
+
+    anim_states: HashMap<Handle<Cocos2dAnimStateMachine>, bool>,
 }
 
 fn check_preload_finished(
@@ -151,12 +148,17 @@ fn check_preload_finished(
 
     loaded_res.map_loaded = false;
     loaded_res.map_handle = asset_server.load("Resources/UI/stages/stage1/game_scene_stage1.tmx");
+
+    for anim_state in [UNIT_ANIM_STATE_MACHINE, PROJECTILE_ANIM_STATE_MACHINE] {
+        loaded_res.anim_states.insert(asset_server.load(anim_state), false);
+    }
 }
 
 fn check_res_load_finished(
     mut anim_events: EventReader<AssetEvent<Cocos2dAnimAsset>>,
     mut audio_events: EventReader<AssetEvent<AudioSource>>,
     mut map_events: EventReader<AssetEvent<TmxMapAsset>>,
+    mut anim_state_events: EventReader<AssetEvent<Cocos2dAnimStateMachine>>,
     mut state: ResMut<NextState<GameStates>>,
     mut loaded_res: ResMut<LoadedResource>,
 ) {
@@ -185,10 +187,19 @@ fn check_res_load_finished(
         }
     }
 
+    for evt in anim_state_events.read() {
+        for (handle, value) in loaded_res.anim_states.iter_mut() {
+            if evt.is_loaded_with_dependencies(handle) {
+                *value = true;
+            }
+        }
+    }
+
 
     if loaded_res.anims.values().all(|v| *v)
         && loaded_res.audios.values().all(|v| *v)
         && loaded_res.map_loaded
+        && loaded_res.anim_states.values().all(|v| *v)
     {
         info!("all res loaded");
         state.set(PrepareScene);
@@ -286,9 +297,6 @@ fn generate_unit(
             Cocos2dAnimator {
                 anim_handle: asset_server.load(unit_info.animation_name.anim_path()),
                 face_dir: AnimationFaceDir::Right,
-                event_channel: Some(AnimChannel::Unit.into()),
-                new_anim: Some(UnitAnimName::Born.into()),
-                mode: AnimationMode::Once,
                 ..default()
             },
         ));
@@ -319,9 +327,6 @@ fn generate_unit(
             Cocos2dAnimator {
                 anim_handle: asset_server.load(unit_info.animation_name.anim_path()),
                 face_dir: AnimationFaceDir::Left,
-                event_channel: Some(AnimChannel::Unit.into()),
-                new_anim: Some(UnitAnimName::Born.into()),
-                mode: AnimationMode::Once,
                 ..default()
             },
         ));
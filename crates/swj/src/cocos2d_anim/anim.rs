@@ -1,3 +1,7 @@
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use bevy::{
     asset::{AssetLoader, io::Reader, LoadContext},
     prelude::*,
@@ -6,36 +10,45 @@ use bevy::{
 };
 use bevy::asset::AsyncReadExt;
 use bevy::math::vec2;
-use bevy::utils::{HashMap};
+use bevy::utils::{HashMap, HashSet};
 use serde_json::Value;
 use thiserror::Error;
 
-use crate::cocos2d_anim::sprite_sheet::PlistSpriteFrameAsset;
+use crate::cocos2d_anim::event::{FrameEventRegistry, TypedFrameEvent};
+use crate::cocos2d_anim::json::{JsonError, JsonErrorReason, JsonNode};
+use crate::cocos2d_anim::sprite_sheet::{PlistSpriteFrameAsset, SpriteFrame};
+use crate::cocos2d_anim::tween::TweenEasing;
 
-struct TextureData {
-    plist_file: String,
-    px: f32,
-    py: f32,
+pub(crate) struct TextureData {
+    pub(crate) plist_file: String,
+    pub(crate) px: f32,
+    pub(crate) py: f32,
 }
 
-struct DisplayData {
-    name: String,
-    xy: Vec2,
-    scale: Vec2,
+pub(crate) struct DisplayData {
+    pub(crate) name: String,
+    pub(crate) xy: Vec2,
+    pub(crate) scale: Vec2,
+    pub(crate) skew: Vec2,
 }
 
-struct BoneData {
-    display_data: Vec<DisplayData>,
-    translate: Vec3,
-    scale: Vec2,
+pub(crate) struct BoneData {
+    pub(crate) parent: Option<String>,
+    pub(crate) display_data: Vec<DisplayData>,
+    pub(crate) translate: Vec3,
+    pub(crate) scale: Vec2,
+    pub(crate) skew: Vec2,
 }
 
 struct MoveBoneFrameData {
+    pointer: String,
     translate: Vec3,
     scale: Vec2,
+    skew: Vec2,
     evt: Option<FrameEvent>,
     color: Option<Color>,
-    di: usize,
+    tween: Option<TweenEasing>,
+    di: Option<usize>,
     fi: usize,
 }
 
@@ -47,23 +60,41 @@ struct MoveBoneData {
 
 
 #[derive(Default)]
-pub struct Cocos2dAnimAssetLoader;
+pub struct Cocos2dAnimAssetLoader {
+    pub events: Arc<FrameEventRegistry>,
+}
 
 #[derive(Debug, Clone)]
 pub enum FrameEvent {
     Perform,
     PerformAt(Vec2),
+    /// an event registered on [`FrameEventRegistry`]
+    Typed(TypedFrameEvent),
+    /// an event nobody registered, warned about at load time
     Other(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Cocos2dAnimFrame {
+    /// bone translate of this keyframe, tweened towards the next keyframe
     pub translate: Vec3,
     pub scale: Vec2,
+    /// bone skew (`kX`, `kY`) in radians
+    pub skew: Vec2,
+    /// skin offset of the displayed sprite in bone space, not tweened
+    pub display_offset: Vec3,
+    pub display_scale: Vec2,
+    pub display_skew: Vec2,
     pub rotated: bool,
+    /// `false` when the bone shows no display on this keyframe (`dI` is -1 or the bone has none)
+    pub visible: bool,
+    /// index into `Cocos2dAnimBone::displays`
+    pub di: Option<usize>,
 
     pub evt: Option<FrameEvent>,
     pub color: Option<Color>,
+    /// easing towards the next keyframe, `None` holds this keyframe
+    pub tween: Option<TweenEasing>,
 
     pub fi: usize,
 
@@ -72,17 +103,591 @@ pub struct Cocos2dAnimFrame {
     pub sprite_idx: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Cocos2dAnimMove {
     pub interval: f32,
     pub frame_size: usize,
     pub layers: HashMap<String, Vec<Cocos2dAnimFrame>>,
 }
 
-#[derive(Asset, TypePath, Debug)]
+/// Bone transform and color of a layer at some point between two keyframes.
+#[derive(Debug, Clone, Copy)]
+pub struct LayerPose {
+    pub translate: Vec3,
+    pub scale: Vec2,
+    pub skew: Vec2,
+    pub color: Color,
+}
+
+impl LayerPose {
+    pub fn rotation(&self) -> f32 {
+        skew_to_rotation(self.skew)
+    }
+
+    /// Affine part of cocos `TransformHelper::nodeToMatrix`.
+    fn matrix(&self) -> Mat2 {
+        Mat2::from_cols(
+            vec2(self.scale.x * self.skew.y.cos(), self.scale.x * self.skew.y.sin()),
+            vec2(self.scale.y * self.skew.x.sin(), self.scale.y * self.skew.x.cos()),
+        )
+    }
+
+    /// Move a bone local pose into the space of its parent's world pose,
+    /// like cocos `Bone::applyParentTransform`. z stays the bone's own,
+    /// color and opacity cascade from the parent like cocos bones do.
+    pub fn with_parent(&self, parent: &LayerPose) -> LayerPose {
+        let xy = parent.matrix() * self.translate.truncate() + parent.translate.truncate();
+
+        LayerPose {
+            translate: xy.extend(self.translate.z),
+            scale: self.scale * parent.scale,
+            skew: self.skew + parent.skew,
+            color: multiply_color(self.color, parent.color),
+        }
+    }
+
+    /// Blend towards `other`, skew takes the shortest arc.
+    pub fn lerp(&self, other: &LayerPose, t: f32) -> LayerPose {
+        LayerPose {
+            translate: self.translate.lerp(other.translate, t),
+            scale: self.scale.lerp(other.scale, t),
+            skew: Vec2::new(
+                lerp_angle(self.skew.x, other.skew.x, t),
+                lerp_angle(self.skew.y, other.skew.y, t),
+            ),
+            color: self.color.mix(&other.color, t),
+        }
+    }
+}
+
+/// Component-wise product in sRGB, like cocos combines node colors.
+pub fn multiply_color(a: Color, b: Color) -> Color {
+    let a = Srgba::from(a);
+    let b = Srgba::from(b);
+
+    Color::srgba(a.red * b.red, a.green * b.green, a.blue * b.blue, a.alpha * b.alpha)
+}
+
+/// Counter-clockwise rotation of a cocos skew pair. The transform can't hold a real skew,
+/// so unequal skews are approximated by the mean rotation of both axes.
+pub fn skew_to_rotation(skew: Vec2) -> f32 {
+    (skew.y - skew.x) / 2.0
+}
+
+/// Lerp between two angles along the shortest arc, like cocostudio does.
+fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    let delta = (to - from + PI).rem_euclid(2.0 * PI) - PI;
+    from + delta * t
+}
+
+/// Index of the keyframe in `frames` that is active at `frame_idx`.
+pub fn key_frame_idx(frames: &[Cocos2dAnimFrame], frame_idx: usize) -> usize {
+    frames.partition_point(|f| f.fi <= frame_idx).saturating_sub(1)
+}
+
+/// Tween keyframe `idx` towards the next keyframe, `frame_pos` is the fractional frame position.
+pub fn sample_layer(frames: &[Cocos2dAnimFrame], idx: usize, frame_pos: f32) -> LayerPose {
+    let frame = &frames[idx];
+    let color = frame.color.unwrap_or(Color::WHITE);
+    let pose = LayerPose {
+        translate: frame.translate,
+        scale: frame.scale,
+        skew: frame.skew,
+        color,
+    };
+
+    let (Some(easing), Some(next)) = (frame.tween, frames.get(idx + 1)) else {
+        return pose;
+    };
+
+    if next.fi <= frame.fi {
+        return pose;
+    }
+
+    let t = (frame_pos - frame.fi as f32) / (next.fi - frame.fi) as f32;
+    let t = easing.ease(t);
+    let next_pose = LayerPose {
+        translate: next.translate,
+        scale: next.scale,
+        skew: next.skew,
+        color: next.color.unwrap_or(Color::WHITE),
+    };
+
+    pose.lerp(&next_pose, t)
+}
+
+/// Skin transform of one entry of a bone's `display_data`.
+#[derive(Debug, Clone)]
+pub struct Cocos2dAnimDisplay {
+    pub name: String,
+    pub xy: Vec2,
+    pub scale: Vec2,
+    pub skew: Vec2,
+    /// texture anchor (`pX`, `pY`)
+    pub anchor: Vec2,
+    /// sheet holding the sprite frame `name`
+    pub sheet: Option<Handle<PlistSpriteFrameAsset>>,
+}
+
+/// Offset in bone space of `sprite_frame` shown with the skin transform of `display`.
+pub fn display_offset(display: &Cocos2dAnimDisplay, sprite_frame: &SpriteFrame) -> Vec3 {
+    let offset = sprite_frame.anchor_offset(display.anchor);
+
+    // the trimmed sprite offset lives in skin space, move it into bone space
+    let display_rotation = Mat2::from_angle(skew_to_rotation(display.skew));
+    (display.xy + display_rotation * (offset * display.scale)).extend(0.0)
+}
+
+pub(crate) fn new_display(dd: &DisplayData,
+                          texture_data: &HashMap<String, TextureData>,
+                          plist_handles: &HashMap<String, Handle<PlistSpriteFrameAsset>>) -> Cocos2dAnimDisplay {
+    let tex_data = texture_data.get(&dd.name);
+
+    Cocos2dAnimDisplay {
+        name: dd.name.clone(),
+        xy: dd.xy,
+        scale: dd.scale,
+        skew: dd.skew,
+        anchor: tex_data.map_or(vec2(0.5, 0.5), |t| vec2(t.px, t.py)),
+        sheet: tex_data.and_then(|t| plist_handles.get(&t.plist_file)).cloned(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cocos2dAnimBone {
+    pub name: String,
+    /// index into `Cocos2dAnimAsset::bones`, a parent always comes before its children
+    pub parent: Option<usize>,
+    /// pose of the bone when no animation moves it
+    pub rest: LayerPose,
+    /// every display the bone can show, frames pick one by `di`
+    pub displays: Vec<Cocos2dAnimDisplay>,
+}
+
+/// One armature of an ExportJson or DragonBones file. The file itself loads as its first armature,
+/// every armature is also a labeled sub-asset, e.g. `hero.ExportJson#summon`.
+#[derive(Asset, TypePath, Debug, Clone)]
 pub struct Cocos2dAnimAsset {
+    pub name: String,
     pub animation: HashMap<String, Cocos2dAnimMove>,
-    plist_handles: Vec<Handle<PlistSpriteFrameAsset>>,
+    pub bones: Vec<Cocos2dAnimBone>,
+    /// all armatures of the file by name, only filled on the file's root asset
+    pub armatures: HashMap<String, Handle<Cocos2dAnimAsset>>,
+    pub(crate) plist_handles: Vec<Handle<PlistSpriteFrameAsset>>,
+}
+
+impl Cocos2dAnimAsset {
+    pub fn armature(&self, name: &str) -> Option<Handle<Cocos2dAnimAsset>> {
+        self.armatures.get(name).cloned()
+    }
+
+    pub fn bone_idx(&self, name: &str) -> Option<usize> {
+        self.bones.iter().position(|b| b.name == name)
+    }
+
+    /// Fill `poses` with the world pose of every bone, in `bones` order.
+    pub fn sample_bones(&self, animation: &Cocos2dAnimMove, frame_idx: usize, frame_pos: f32, poses: &mut Vec<LayerPose>) {
+        poses.clear();
+
+        for bone in &self.bones {
+            let local = match animation.layers.get(&bone.name) {
+                Some(frames) if !frames.is_empty() => {
+                    sample_layer(frames, key_frame_idx(frames, frame_idx), frame_pos)
+                }
+                _ => bone.rest,
+            };
+
+            let pose = match bone.parent {
+                Some(parent) => local.with_parent(&poses[parent]),
+                None => local,
+            };
+
+            poses.push(pose);
+        }
+    }
+
+    /// Fill `ranks` with the draw rank of every bone at `frame_idx`, by keyframe z and then bone order.
+    pub fn draw_order(&self, animation: &Cocos2dAnimMove, frame_idx: usize, order: &mut Vec<usize>, ranks: &mut Vec<usize>) {
+        let z = |bone: &Cocos2dAnimBone| match animation.layers.get(&bone.name) {
+            Some(frames) if !frames.is_empty() => frames[key_frame_idx(frames, frame_idx)].translate.z,
+            _ => bone.rest.translate.z,
+        };
+
+        order.clear();
+        order.extend(0..self.bones.len());
+        order.sort_by(|a, b| z(&self.bones[*a]).total_cmp(&z(&self.bones[*b])).then(a.cmp(b)));
+
+        ranks.clear();
+        ranks.resize(self.bones.len(), 0);
+        for (rank, bone) in order.iter().enumerate() {
+            ranks[*bone] = rank;
+        }
+    }
+
+    /// Events of the keyframes that start at `frame_idx`, in `bones` order.
+    pub fn events_at<'a>(&'a self, animation: &'a Cocos2dAnimMove, frame_idx: usize) -> impl Iterator<Item=&'a FrameEvent> + 'a {
+        self.bones.iter()
+            .filter_map(move |bone| animation.layers.get(&bone.name))
+            .filter_map(move |frames| {
+                let frame = frames.get(key_frame_idx(frames, frame_idx))?;
+                if frame.fi == frame_idx {
+                    frame.evt.as_ref()
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+/// Order bones so that parents come first, unknown parents and cycles fall back to root bones.
+pub(crate) fn sort_bones(bone_names: &[String], bone_data: &HashMap<String, BoneData>, file: &Path) -> Vec<Cocos2dAnimBone> {
+    fn new_bone(name: &str, bd: &BoneData, parent: Option<usize>) -> Cocos2dAnimBone {
+        Cocos2dAnimBone {
+            name: name.to_string(),
+            parent,
+            rest: LayerPose {
+                translate: bd.translate,
+                scale: bd.scale,
+                skew: bd.skew,
+                color: Color::WHITE,
+            },
+            displays: Vec::new(),
+        }
+    }
+
+    let mut bones: Vec<Cocos2dAnimBone> = Vec::with_capacity(bone_names.len());
+    let mut pending: Vec<&String> = bone_names.iter().collect();
+
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut next_pending = Vec::new();
+
+        for name in pending {
+            let bd = &bone_data[name];
+            let parent = match &bd.parent {
+                Some(parent) if bone_data.contains_key(parent) => {
+                    match bones.iter().position(|b| b.name == *parent) {
+                        Some(idx) => Some(idx),
+                        None => {
+                            next_pending.push(name);
+                            continue;
+                        }
+                    }
+                }
+                Some(parent) => {
+                    warn!("bone {} has unknown parent {} in {}, treat it as a root bone.", name, parent, file.display());
+                    None
+                }
+                None => None,
+            };
+
+            bones.push(new_bone(name, bd, parent));
+        }
+
+        if next_pending.len() == before {
+            warn!("bones {:?} form a parent cycle in {}, treat them as root bones.", next_pending, file.display());
+            for name in next_pending.drain(..) {
+                bones.push(new_bone(name, &bone_data[name], None));
+            }
+        }
+
+        pending = next_pending;
+    }
+
+    bones
+}
+
+
+/// Read the `kX`/`kY` skew of a node, older exporters write `skX`/`skY`.
+fn json_skew(node: &JsonNode) -> Result<Vec2, JsonError> {
+    let read = |keys: [&str; 2]| {
+        match keys.iter().find_map(|k| node.opt(k)) {
+            Some(v) => v.f32(),
+            None => Ok(0.0),
+        }
+    };
+
+    Ok(Vec2::new(read(["kX", "skX"])?, read(["kY", "skY"])?))
+}
+
+fn json_vec2(node: &JsonNode, x: &str, y: &str) -> Result<Vec2, JsonError> {
+    Ok(Vec2::new(node.get(x)?.f32()?, node.get(y)?.f32()?))
+}
+
+/// `unknown_events` holds the unregistered names already reported for this file.
+fn parse_frame_event(frame: &JsonNode, translate: Vec3, events: &FrameEventRegistry,
+                     unknown_events: &mut HashSet<String>, path: &Path) -> Result<Option<FrameEvent>, JsonError> {
+    let (evt, msg) = match frame.opt("evt") {
+        Some(evt) => match evt.value().as_str() {
+            Some(msg) => (evt.clone(), msg),
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+
+    match events.parse(msg, translate.truncate(), frame.value()) {
+        Some(Ok(evt)) => Ok(Some(evt)),
+        Some(Err(reason)) => Err(evt.error(JsonErrorReason::BadValue(reason))),
+        None => {
+            if unknown_events.insert(msg.to_string()) {
+                warn!("{}: {}: frame event {} is not registered, later uses in this file are not reported", path.display(), evt.pointer(), msg);
+            }
+            Ok(Some(FrameEvent::Other(msg.to_string())))
+        }
+    }
+}
+
+fn parse_armature(armature: &JsonNode,
+                  animation: &JsonNode,
+                  texture_data: &HashMap<String, TextureData>,
+                  plist_file_data: &HashMap<String, PlistSpriteFrameAsset>,
+                  plist_handles: &HashMap<String, Handle<PlistSpriteFrameAsset>>,
+                  events: &FrameEventRegistry,
+                  unknown_events: &mut HashSet<String>,
+                  path: &Path)
+                  -> Result<(HashMap<String, Cocos2dAnimMove>, Vec<Cocos2dAnimBone>), Cocos2dAnimLoaderError> {
+    let mut bone_data = HashMap::new();
+    let mut bone_names = Vec::new();
+
+    for bd in armature.get("bone_data")?.items()? {
+        let name = bd.get("name")?.str()?.to_string();
+        let parent = bd.opt("parent")
+            .and_then(|p| p.value().as_str())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string());
+        let translate = json_vec2(&bd, "x", "y")?.extend(bd.get("z")?.f32()?);
+        let scale = json_vec2(&bd, "cX", "cY")?;
+        let skew = json_skew(&bd)?;
+        let mut display_data = Vec::new();
+
+        for dd in bd.get("display_data")?.items()? {
+            let name = dd.get("name")?.str()?.replace(".png", "");
+
+            let skin_list = dd.get("skin_data")?;
+            let skin_data = skin_list.items()?;
+            let skin_data = skin_data.first()
+                .ok_or_else(|| skin_list.error(JsonErrorReason::BadValue("empty skin_data".to_string())))?;
+            let xy = json_vec2(skin_data, "x", "y")?;
+            let scale = json_vec2(skin_data, "cX", "cY")?;
+            let skew = json_skew(skin_data)?;
+
+            display_data.push(DisplayData {
+                name,
+                xy,
+                scale,
+                skew,
+            });
+        }
+
+
+        let data = BoneData {
+            parent,
+            display_data,
+            translate,
+            scale,
+            skew,
+        };
+
+        bone_names.push(name.clone());
+        bone_data.insert(name, data);
+    }
+
+    let mut bones = sort_bones(&bone_names, &bone_data, path);
+    for bone in bones.iter_mut() {
+        bone.displays = bone_data[&bone.name].display_data.iter()
+            .map(|dd| new_display(dd, texture_data, plist_handles))
+            .collect();
+    }
+
+
+    let mut move_bone_data = HashMap::new();
+
+    for mbd in animation.get("mov_data")?.items()? {
+        let name = mbd.get("name")?.str()?.to_string();
+        let interval = mbd.get("sc")?.f32()?;
+        let interval = interval * 60.0;
+        let interval = 1.0 / interval * 2.0;
+        let frame_size = mbd.get("dr")?.f32()? as usize + 1;
+        let mut layers = HashMap::new();
+
+        let mov_bone_data = if let Some(mov_bone_data) = mbd.opt("mov_bone_data") {
+            mov_bone_data
+        } else {
+            continue;
+        };
+
+        for layer in mov_bone_data.items()? {
+            let name = layer.get("name")?.str()?.to_string();
+            if !bone_data.contains_key(&name) {
+                return Err(Cocos2dAnimLoaderError::UnknownBone {
+                    path: path.to_path_buf(),
+                    pointer: layer.pointer().to_string(),
+                    name,
+                });
+            }
+
+            let mut layer_data = Vec::new();
+
+            for frame in layer.get("frame_data")?.items()? {
+                let di = frame.get("dI")?.f32()?;
+                let di = if di < 0.0 { None } else { Some(di as usize) };
+                let fi = frame.get("fi")?.f32()? as usize;
+                let translate = json_vec2(&frame, "x", "y")?.extend(frame.get("z")?.f32()?);
+                let scale = json_vec2(&frame, "cX", "cY")?;
+                let skew = json_skew(&frame)?;
+                let evt = parse_frame_event(&frame, translate, events, unknown_events, path)?;
+                let tween_frame = match frame.opt("tweenFrame") {
+                    Some(v) => v.bool()?,
+                    None => true,
+                };
+                let tween = if tween_frame {
+                    TweenEasing::from_cocos(frame.opt("twE").map_or(Ok(0), |v| v.i64())?)
+                } else {
+                    None
+                };
+                let color = if let Some(color) = frame.opt("color") {
+                    Some(Color::srgba(
+                        color.get("r")?.f32()? / 255.0,
+                        color.get("g")?.f32()? / 255.0,
+                        color.get("b")?.f32()? / 255.0,
+                        color.get("a")?.f32()? / 255.0,
+                    ))
+                } else {
+                    None
+                };
+
+
+                layer_data.push(MoveBoneFrameData {
+                    pointer: frame.pointer().to_string(),
+                    translate,
+                    scale,
+                    skew,
+                    evt,
+                    color,
+                    tween,
+                    di,
+                    fi,
+                });
+            }
+
+            layers.insert(name, layer_data);
+        }
+
+        let data = MoveBoneData {
+            layers,
+            interval,
+            frame_size,
+        };
+
+        move_bone_data.insert(name, data);
+    }
+
+
+    let mut animation = HashMap::new();
+
+    for (name, mbd) in move_bone_data {
+        let mut layer_map = HashMap::new();
+
+        for (layer_name, layer_data) in mbd.layers {
+            let mut frames = Vec::new();
+
+            for frame_data in layer_data {
+                let bd = &bone_data[&layer_name];
+                let translate = bd.translate + frame_data.translate;
+                let scale = bd.scale * frame_data.scale;
+                let skew = bd.skew + frame_data.skew;
+
+                let dd = if let Some(di) = frame_data.di {
+                    bd.display_data.get(di)
+                } else {
+                    None
+                };
+
+                let dd = if let Some(dd) = dd {
+                    dd
+                } else {
+                    // a bone without display still moves its children
+                    frames.push(Cocos2dAnimFrame {
+                        translate,
+                        scale,
+                        skew,
+                        display_offset: Vec3::ZERO,
+                        display_scale: Vec2::ONE,
+                        display_skew: Vec2::ZERO,
+                        rotated: false,
+                        visible: false,
+                        di: None,
+                        evt: frame_data.evt,
+                        color: frame_data.color,
+                        tween: frame_data.tween,
+                        fi: frame_data.fi,
+                        sprite_atlas: Handle::default(),
+                        texture: Handle::default(),
+                        sprite_idx: 0,
+                    });
+                    continue;
+                };
+
+                let tex_data = texture_data.get(&dd.name)
+                    .ok_or_else(|| Cocos2dAnimLoaderError::UnknownTexture {
+                        path: path.to_path_buf(),
+                        pointer: frame_data.pointer.clone(),
+                        name: dd.name.clone(),
+                    })?;
+                let sprite_sheet = plist_file_data.get(&tex_data.plist_file)
+                    .ok_or_else(|| Cocos2dAnimLoaderError::UnknownSpriteSheet {
+                        path: path.to_path_buf(),
+                        pointer: frame_data.pointer.clone(),
+                        name: dd.name.clone(),
+                        sheet: tex_data.plist_file.clone(),
+                    })?;
+                let sprite_idx = sprite_sheet.frame_index(&dd.name)
+                    .ok_or_else(|| Cocos2dAnimLoaderError::UnknownSpriteFrame {
+                        path: path.to_path_buf(),
+                        pointer: frame_data.pointer.clone(),
+                        name: dd.name.clone(),
+                        sheet: tex_data.plist_file.clone(),
+                    })?;
+                let sprite_frame = &sprite_sheet.frames[sprite_idx];
+                let display = new_display(dd, texture_data, plist_handles);
+                let display_offset = display_offset(&display, sprite_frame);
+
+
+                let frame = Cocos2dAnimFrame {
+                    translate,
+                    scale,
+                    skew,
+                    display_offset,
+                    display_scale: dd.scale,
+                    display_skew: dd.skew,
+                    rotated: sprite_frame.rotated,
+                    visible: true,
+                    di: frame_data.di,
+                    evt: frame_data.evt,
+                    color: frame_data.color,
+                    tween: frame_data.tween,
+                    fi: frame_data.fi,
+                    sprite_atlas: sprite_sheet.atlas.clone(),
+                    texture: sprite_sheet.texture.clone(),
+                    sprite_idx,
+                };
+
+                frames.push(frame);
+            }
+
+            layer_map.insert(layer_name, frames);
+        }
+
+        let data = Cocos2dAnimMove {
+            interval: mbd.interval,
+            frame_size: mbd.frame_size,
+            layers: layer_map,
+        };
+
+        animation.insert(name, data);
+    }
+
+    Ok((animation, bones))
 }
 
 #[non_exhaustive]
@@ -91,6 +696,50 @@ pub enum Cocos2dAnimLoaderError {
     /// An [IO](std::io) Error
     #[error("Could load asset: {0}")]
     Io(#[from] std::io::Error),
+    #[error("{}: invalid json: {source}", .path.display())]
+    Syntax {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A missing or malformed field
+    #[error(transparent)]
+    Field(#[from] JsonError),
+    #[error("{}: can't load sprite sheet {sheet}: {reason}", .path.display())]
+    SpriteSheet {
+        path: PathBuf,
+        sheet: String,
+        reason: String,
+    },
+    #[error("{}: {pointer}: unknown bone {name}", .path.display())]
+    UnknownBone {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+    },
+    #[error("{}: {pointer}: unknown texture name {name}", .path.display())]
+    UnknownTexture {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+    },
+    #[error("{}: {pointer}: texture {name} uses sprite sheet {sheet} that is not in config_file_path", .path.display())]
+    UnknownSpriteSheet {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+        sheet: String,
+    },
+    #[error("{}: {pointer}: sprite frame {name} not found in {sheet}", .path.display())]
+    UnknownSpriteFrame {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+        sheet: String,
+    },
+    #[error("{}: armature_data is empty", .path.display())]
+    NoArmature {
+        path: PathBuf,
+    },
 }
 
 impl AssetLoader for Cocos2dAnimAssetLoader {
@@ -105,43 +754,56 @@ impl AssetLoader for Cocos2dAnimAssetLoader {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let json_str = String::from_utf8(bytes).unwrap();
-            let anim_data: HashMap<String, Value> = serde_json::from_str(&json_str).unwrap();
 
-            let plist_file_names = anim_data["config_file_path"].as_array().unwrap()
+            let path = load_context.path().to_path_buf();
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let anim_data: Value = serde_json::from_slice(&bytes)
+                .map_err(|source| Cocos2dAnimLoaderError::Syntax { path: path.clone(), source })?;
+            let anim_data = JsonNode::root(&path, &anim_data);
+
+            let plist_file_names = anim_data.get("config_file_path")?.items()?
                 .iter()
-                .map(|v| v.as_str().unwrap().to_string())
-                .collect::<Vec<String>>();
+                .map(|v| v.str().map(|s| s.to_string()))
+                .collect::<Result<Vec<String>, _>>()?;
 
             let mut plist_file_data: HashMap<String, PlistSpriteFrameAsset> = HashMap::new();
             let mut plist_handles = Vec::new();
             for plist_file_name in plist_file_names.iter() {
-                let sprite_sheet = load_context.load_direct(
-                    load_context.path().parent().unwrap().join(plist_file_name.clone()),
-                ).await.unwrap();
-
-                let sprite_sheet = sprite_sheet.get::<PlistSpriteFrameAsset>().unwrap();
+                let sprite_sheet = load_context.load_direct(dir.join(plist_file_name)).await
+                    .map_err(|e| Cocos2dAnimLoaderError::SpriteSheet {
+                        path: path.clone(),
+                        sheet: plist_file_name.clone(),
+                        reason: e.to_string(),
+                    })?;
+
+                let sprite_sheet = sprite_sheet.get::<PlistSpriteFrameAsset>()
+                    .ok_or_else(|| Cocos2dAnimLoaderError::SpriteSheet {
+                        path: path.clone(),
+                        sheet: plist_file_name.clone(),
+                        reason: "not a sprite sheet".to_string(),
+                    })?;
                 plist_file_data.insert(plist_file_name.clone(), sprite_sheet.clone());
             }
 
+            let mut plist_handle_map = HashMap::new();
             for plist_file_name in plist_file_names.iter() {
-                let handle = load_context.load(load_context.path().parent().unwrap().join(plist_file_name.clone()));
+                let handle = load_context.load(dir.join(plist_file_name));
+                plist_handle_map.insert(plist_file_name.clone(), handle.clone());
                 plist_handles.push(handle);
             }
 
 
             let mut texture_data = HashMap::new();
 
-            for ta in anim_data["texture_data"].as_array().unwrap() {
-                let ta = ta.as_object().unwrap();
-                let name = ta["name"].as_str().unwrap().to_string();
-                let mut plist_file = ta["plistFile"].as_str().unwrap().to_string();
-                let px = ta["pX"].as_f64().unwrap() as f32;
-                let py = ta["pY"].as_f64().unwrap() as f32;
+            for ta in anim_data.get("texture_data")?.items()? {
+                let name = ta.get("name")?.str()?.to_string();
+                let mut plist_file = ta.get("plistFile")?.str()?.to_string();
+                let px = ta.get("pX")?.f32()?;
+                let py = ta.get("pY")?.f32()?;
 
                 if plist_file.is_empty() {
                     for (plist_name, plist_frame) in plist_file_data.iter() {
-                        if plist_frame.frames.iter().any(|sf| sf.name == name) {
+                        if plist_frame.frame_index(&name).is_some() {
                             plist_file = plist_name.clone();
                             break;
                         }
@@ -150,7 +812,7 @@ impl AssetLoader for Cocos2dAnimAssetLoader {
                     if plist_file.is_empty() {
                         warn!("plist_file is empty and can't be found in all loaded plist files.\
                         Animation file: {}, name: {}",
-                               load_context.path().to_str().unwrap(),
+                               path.display(),
                                name);
                     }
                 }
@@ -164,220 +826,47 @@ impl AssetLoader for Cocos2dAnimAssetLoader {
                 texture_data.insert(name, data);
             }
 
-            let mut bone_data = HashMap::new();
-
-            for bd in anim_data["armature_data"].as_array().unwrap().first().unwrap()["bone_data"].as_array().unwrap() {
-                let bd = bd.as_object().unwrap();
-                let name = bd["name"].as_str().unwrap().to_string();
-                let translate = Vec3::new(
-                    bd["x"].as_f64().unwrap() as f32,
-                    bd["y"].as_f64().unwrap() as f32,
-                    bd["z"].as_f64().unwrap() as f32,
-                );
-                let scale = Vec2::new(
-                    bd["cX"].as_f64().unwrap() as f32,
-                    bd["cY"].as_f64().unwrap() as f32,
-                );
-                let mut display_data = Vec::new();
-
-                for dd in bd["display_data"].as_array().unwrap() {
-                    let dd = dd.as_object().unwrap();
-                    let name = dd["name"].as_str().unwrap().to_string();
-                    let name = name.replace(".png", "");
-
-                    let skin_data = dd["skin_data"].as_array().unwrap().first().unwrap();
-                    let xy = Vec2::new(
-                        skin_data["x"].as_f64().unwrap() as f32,
-                        skin_data["y"].as_f64().unwrap() as f32,
-                    );
-                    let scale = Vec2::new(
-                        skin_data["cX"].as_f64().unwrap() as f32,
-                        skin_data["cY"].as_f64().unwrap() as f32,
-                    );
-
-                    display_data.push(DisplayData {
-                        name,
-                        xy,
-                        scale,
-                    });
-                }
-
-
-                let data = BoneData {
-                    display_data,
-                    translate,
-                    scale,
+            let armature_list = anim_data.get("armature_data")?.items()?;
+            let animation_list = anim_data.get("animation_data")?.items()?;
+
+            let mut root = None;
+            let mut armatures = HashMap::new();
+            let mut unknown_events = HashSet::new();
+
+            for (idx, armature) in armature_list.iter().enumerate() {
+                let name = armature.get("name")?.str()?.to_string();
+
+                // animation_data is matched by armature name, older exports rely on the order
+                let animation = animation_list.iter()
+                    .find(|a| a.value()["name"].as_str() == Some(name.as_str()))
+                    .or_else(|| animation_list.get(idx))
+                    .ok_or_else(|| armature.error(JsonErrorReason::BadValue(
+                        format!("no animation_data for armature {}", name)
+                    )))?;
+
+                let (animation, bones) = parse_armature(armature, animation, &texture_data, &plist_file_data, &plist_handle_map,
+                                                    &self.events, &mut unknown_events, &path)?;
+
+                let asset = Cocos2dAnimAsset {
+                    name: name.clone(),
+                    animation,
+                    bones,
+                    armatures: HashMap::new(),
+                    plist_handles: plist_handles.clone(),
                 };
 
-                bone_data.insert(name, data);
-            }
-
-
-            let mut move_bone_data = HashMap::new();
-
-            for mbd in anim_data["animation_data"].as_array().unwrap().first().unwrap()["mov_data"].as_array().unwrap() {
-                let mbd = mbd.as_object().unwrap();
-                let name = mbd["name"].as_str().unwrap().to_string();
-                let interval = mbd["sc"].as_f64().unwrap() as f32;
-                let interval = interval * 60.0;
-                let interval = 1.0 / interval * 2.0;
-                let frame_size = mbd["dr"].as_f64().unwrap() as usize + 1;
-                let mut layers = HashMap::new();
-
-                if mbd.get("mov_bone_data").is_none() {
-                    continue;
+                if root.is_none() {
+                    root = Some(asset.clone());
                 }
 
-                for layer in mbd["mov_bone_data"].as_array().unwrap() {
-                    let layer = layer.as_object().unwrap();
-                    let name = layer["name"].as_str().unwrap().to_string();
-                    let mut layer_data = Vec::new();
-
-                    for frame in layer["frame_data"].as_array().unwrap() {
-                        let frame = frame.as_object().unwrap();
-                        let di = frame["dI"].as_f64().unwrap() as usize;
-                        let fi = frame["fi"].as_f64().unwrap() as usize;
-                        let translate = Vec3::new(
-                            frame["x"].as_f64().unwrap() as f32,
-                            frame["y"].as_f64().unwrap() as f32,
-                            frame["z"].as_f64().unwrap() as f32,
-                        );
-                        let scale = Vec2::new(
-                            frame["cX"].as_f64().unwrap() as f32,
-                            frame["cY"].as_f64().unwrap() as f32,
-                        );
-                        let evt = if let Some(evt) = frame.get("evt") {
-                            let msg = evt.as_str().map(|s| s.to_string());
-                            if let Some(msg) = msg {
-                                if msg == "perform" {
-                                    Some(FrameEvent::Perform)
-                                } else if msg.starts_with("perform#") {
-                                    let perform_offset = if let Some(offset) = frame.get("perform_offset") {
-                                        let offset_str = offset.as_str().unwrap();
-                                        let offset = offset_str.split(",").map(|s| s.parse::<f32>().unwrap()).collect::<Vec<f32>>();
-                                        if offset.len() != 2 {
-                                            panic!("perform# event format error: {}, perform_offset: {}, should have 2 values", msg, offset_str);
-                                        }
-                                        Vec2::new(offset[0], offset[1])
-                                    } else {
-                                        warn!("perform# event format error: {}, perform# should have perform_offset", msg);
-                                        Vec2::ZERO
-                                    };
-                                    // info!("perform# event: {}, perform_offset: {:?}", msg, perform_offset);
-                                    Some(FrameEvent::PerformAt(translate.truncate() + perform_offset))
-                                    // Some(FrameEvent::PerformAt(perform_offset))
-                                } else {
-                                    Some(FrameEvent::Other(msg))
-                                }
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        };
-                        let color = if let Some(color) = frame.get("color") {
-                            color.as_object().map(|a| {
-                                Color::rgba(
-                                    a["a"].as_f64().unwrap() as f32 / 255.0,
-                                    a["r"].as_f64().unwrap() as f32 / 255.0,
-                                    a["g"].as_f64().unwrap() as f32 / 255.0,
-                                    a["b"].as_f64().unwrap() as f32 / 255.0,
-                                )
-                            })
-                        } else {
-                            None
-                        };
-
-
-                        layer_data.push(MoveBoneFrameData {
-                            translate,
-                            scale,
-                            evt,
-                            color,
-                            di,
-                            fi,
-                        });
-                    }
-
-                    layers.insert(name, layer_data);
-                }
-
-                let data = MoveBoneData {
-                    layers,
-                    interval,
-                    frame_size,
-                };
-
-                move_bone_data.insert(name, data);
+                let handle = load_context.add_labeled_asset(name.clone(), asset);
+                armatures.insert(name, handle);
             }
 
+            let mut root = root.ok_or(Cocos2dAnimLoaderError::NoArmature { path: path.clone() })?;
+            root.armatures = armatures;
 
-            let mut animation = HashMap::new();
-
-            for (name, mbd) in move_bone_data {
-                let mut layer_map = HashMap::new();
-
-                for (layer_name, layer_data) in mbd.layers {
-                    let mut frames = Vec::new();
-
-                    for frame_data in layer_data {
-                        let bd = &bone_data[&layer_name];
-                        let dd = &bd.display_data[frame_data.di];
-                        let tex_data = &texture_data[&dd.name];
-                        let sprite_sheet = &plist_file_data[&tex_data.plist_file];
-                        let sprite_idx = sprite_sheet.frames.iter().position(|sf| sf.name == dd.name).unwrap();
-                        let sprite_frame = &sprite_sheet.frames[sprite_idx];
-                        let mut offset = Vec2::from(sprite_frame.offset);
-                        let frame_size = Vec2::from(sprite_frame.source_size);
-
-                        let frame_center = frame_size / 2.0;
-                        let anchor = frame_size * vec2(tex_data.px, tex_data.py);
-                        let anchor = anchor.round();
-                        offset = offset - (anchor - frame_center);
-
-
-                        let mut translate = offset.extend(0.0);
-                        translate += dd.xy.extend(0.0);
-                        translate += bd.translate;
-                        translate += frame_data.translate;
-
-                        let mut scale = bd.scale;
-                        scale *= dd.scale;
-                        scale *= frame_data.scale;
-
-
-                        let frame = Cocos2dAnimFrame {
-                            translate,
-                            scale,
-                            rotated: sprite_frame.rotated,
-                            evt: frame_data.evt,
-                            color: frame_data.color,
-                            fi: frame_data.fi,
-                            sprite_atlas: sprite_sheet.atlas.clone(),
-                            texture: sprite_sheet.texture.clone(),
-                            sprite_idx,
-                        };
-
-                        frames.push(frame);
-                    }
-
-                    layer_map.insert(layer_name, frames);
-                }
-
-                let data = Cocos2dAnimMove {
-                    interval: mbd.interval,
-                    frame_size: mbd.frame_size,
-                    layers: layer_map,
-                };
-
-                animation.insert(name, data);
-            }
-
-
-            Ok(Cocos2dAnimAsset {
-                animation,
-                plist_handles,
-            })
+            Ok(root)
         })
     }
 
@@ -0,0 +1,88 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::cocos2d_anim::{AnimationFaceDir, Cocos2dAnimator, Cocos2dAnimatorPlayer};
+use crate::cocos2d_anim::anim::{Cocos2dAnimAsset, LayerPose};
+
+/// Transform of a bone pose in the animator's space. Facing left mirrors it,
+/// the negative x scale flips whatever is put into that space too.
+fn bone_transform(pose: &LayerPose, face_dir: &AnimationFaceDir) -> Transform {
+    let transform = Transform {
+        translation: pose.translate,
+        rotation: Quat::from_rotation_z(pose.rotation()),
+        scale: pose.scale.extend(1.0),
+    };
+
+    match face_dir {
+        AnimationFaceDir::Left => Transform {
+            translation: Vec3::new(-transform.translation.x, transform.translation.y, transform.translation.z),
+            rotation: Quat::from_rotation_z(-pose.rotation()),
+            scale: Vec3::new(-transform.scale.x, transform.scale.y, 1.0),
+        },
+        AnimationFaceDir::Right => transform,
+    }
+}
+
+/// Current bone transforms of animated entities, as of the last animation update.
+#[derive(SystemParam)]
+pub struct AnimBones<'w, 's> {
+    animations: Res<'w, Assets<Cocos2dAnimAsset>>,
+    animators: Query<'w, 's, (&'static Cocos2dAnimator, &'static Cocos2dAnimatorPlayer, &'static GlobalTransform)>,
+}
+
+impl<'w, 's> AnimBones<'w, 's> {
+    /// Transform of `bone` relative to the animator entity.
+    pub fn local(&self, entity: Entity, bone: &str) -> Option<Transform> {
+        let (animator, player, _) = self.animators.get(entity).ok()?;
+        let asset = self.animations.get(&animator.anim_handle)?;
+        let pose = player.poses.get(asset.bone_idx(bone)?)?;
+
+        Some(bone_transform(pose, &animator.face_dir))
+    }
+
+    /// World transform of `bone`, based on the animator's `GlobalTransform` of the last propagation.
+    pub fn world(&self, entity: Entity, bone: &str) -> Option<GlobalTransform> {
+        let local = self.local(entity, bone)?;
+        let (_, _, global) = self.animators.get(entity).ok()?;
+
+        Some(global.mul_transform(local))
+    }
+}
+
+/// Keeps the entity on a bone of the `target` animator. Works for children of the target,
+/// which follow in the same frame, and for other entities, which lag one transform propagation.
+#[derive(Component, Debug)]
+pub struct AttachToBone {
+    pub target: Entity,
+    pub bone: String,
+    /// offset in bone space
+    pub offset: Transform,
+}
+
+pub(crate) fn attach_to_bone(
+    bones: AnimBones,
+    mut query: Query<(&AttachToBone, &mut Transform, Option<&Parent>)>,
+    parents: Query<&GlobalTransform>,
+) {
+    for (attach, mut transform, parent) in &mut query {
+        let parent = parent.map(|p| p.get());
+        let attached = if parent == Some(attach.target) {
+            bones.local(attach.target, &attach.bone)
+                .map(|local| local * attach.offset)
+        } else {
+            bones.world(attach.target, &attach.bone)
+                .map(|world| {
+                    let world = world.mul_transform(attach.offset);
+                    // under some other parent, the transform is in that parent's space
+                    match parent.and_then(|p| parents.get(p).ok()) {
+                        Some(parent_global) => world.reparented_to(parent_global),
+                        None => world.compute_transform(),
+                    }
+                })
+        };
+
+        if let Some(attached) = attached {
+            *transform = attached;
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetLoader, io::Reader, LoadContext},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    utils::BoxedFuture,
+};
+use bevy::asset::AsyncReadExt;
+use flate2::read::ZlibDecoder;
+use thiserror::Error;
+
+const CCZ_HEADER_LEN: usize = 16;
+const PVR_HEADER_LEN: usize = 52;
+const PVR3_VERSION: u32 = 0x03525650;
+const PVR3_PREMULTIPLIED: u32 = 0x02;
+/// Capacity reserved up front, the rest grows with the data actually decompressed.
+const MAX_RESERVED_LEN: usize = 16 << 20;
+
+#[derive(Debug, Clone, Copy)]
+enum PvrPixelFormat {
+    Rgba8888,
+    Bgra8888,
+    Rgba4444,
+    Rgb565,
+}
+
+impl PvrPixelFormat {
+    fn pixel_size(&self) -> usize {
+        match self {
+            PvrPixelFormat::Rgba8888 | PvrPixelFormat::Bgra8888 => 4,
+            PvrPixelFormat::Rgba4444 | PvrPixelFormat::Rgb565 => 2,
+        }
+    }
+}
+
+struct PvrHeader {
+    width: u32,
+    height: u32,
+    format: PvrPixelFormat,
+    /// offset of the first mip level
+    offset: usize,
+    premultiplied: bool,
+}
+
+/// Loads the `.pvr.ccz` textures of cocos projects: a PVR (v2 or v3) compressed with zlib
+/// behind a 16 byte CCZ header. Sheets can name them in `realTextureFileName` like any other image.
+#[derive(Default)]
+pub struct CczTextureLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum CczTextureLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{}: {reason}", .path.display())]
+    BadHeader {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    #[error("{}: invalid zlib data: {source}", .path.display())]
+    Decompress {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Compressed (PVRTC, ETC) and the less common uncompressed layouts
+    #[error("{}: unsupported pixel format {format}", .path.display())]
+    UnsupportedFormat {
+        path: PathBuf,
+        format: String,
+    },
+}
+
+fn u32_le(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn decompress_ccz(bytes: &[u8], path: &Path) -> Result<Vec<u8>, CczTextureLoaderError> {
+    let bad_header = |reason| CczTextureLoaderError::BadHeader { path: path.to_path_buf(), reason };
+
+    if bytes.len() < CCZ_HEADER_LEN {
+        return Err(bad_header("file too short for a ccz header"));
+    }
+    match &bytes[..4] {
+        b"CCZ!" => {}
+        b"CCZp" => return Err(bad_header("encrypted ccz is not supported")),
+        _ => return Err(bad_header("not a ccz file")),
+    }
+    // the ccz header is big endian
+    if u16::from_be_bytes([bytes[4], bytes[5]]) != 0 {
+        return Err(bad_header("ccz compression is not zlib"));
+    }
+    let len = u32::from_be_bytes(bytes[12..16].try_into().unwrap()) as usize;
+
+    // don't trust the header with the allocation, and stop right after the length it claims
+    let mut data = Vec::with_capacity(len.min(MAX_RESERVED_LEN));
+    let decoder = ZlibDecoder::new(&bytes[CCZ_HEADER_LEN..]);
+    std::io::Read::read_to_end(&mut std::io::Read::take(decoder, len as u64 + 1), &mut data)
+        .map_err(|source| CczTextureLoaderError::Decompress { path: path.to_path_buf(), source })?;
+    if data.len() != len {
+        return Err(bad_header("ccz data length doesn't match the header"));
+    }
+
+    Ok(data)
+}
+
+fn parse_pvr_header(data: &[u8], path: &Path) -> Result<PvrHeader, CczTextureLoaderError> {
+    let unsupported = |format: String| CczTextureLoaderError::UnsupportedFormat { path: path.to_path_buf(), format };
+
+    if data.len() < PVR_HEADER_LEN {
+        return Err(CczTextureLoaderError::BadHeader { path: path.to_path_buf(), reason: "file too short for a pvr header" });
+    }
+
+    if u32_le(data, 0) == PVR3_VERSION {
+        // the channel names and bits per channel of uncompressed formats
+        let format = match u64::from_le_bytes(data[8..16].try_into().unwrap()) {
+            0x0808_0808_6162_6772 => PvrPixelFormat::Rgba8888,
+            0x0808_0808_6172_6762 => PvrPixelFormat::Bgra8888,
+            0x0404_0404_6162_6772 => PvrPixelFormat::Rgba4444,
+            0x0005_0605_0062_6772 => PvrPixelFormat::Rgb565,
+            format => return Err(unsupported(format!("pvr3 {:#018x}", format))),
+        };
+        let metadata_size = u32_le(data, 48) as usize;
+
+        Ok(PvrHeader {
+            width: u32_le(data, 28),
+            height: u32_le(data, 24),
+            format,
+            offset: PVR_HEADER_LEN + metadata_size,
+            premultiplied: u32_le(data, 4) & PVR3_PREMULTIPLIED != 0,
+        })
+    } else if &data[44..48] == b"PVR!" {
+        let format = match u32_le(data, 16) & 0xff {
+            0x10 => PvrPixelFormat::Rgba4444,
+            0x12 => PvrPixelFormat::Rgba8888,
+            0x13 => PvrPixelFormat::Rgb565,
+            0x1a => PvrPixelFormat::Bgra8888,
+            format => return Err(unsupported(format!("pvr2 {:#04x}", format))),
+        };
+
+        // v2 has no premultiplied flag, cocos leaves it to a global setting
+        Ok(PvrHeader {
+            width: u32_le(data, 8),
+            height: u32_le(data, 4),
+            format,
+            offset: u32_le(data, 0) as usize,
+            premultiplied: false,
+        })
+    } else {
+        Err(CczTextureLoaderError::BadHeader { path: path.to_path_buf(), reason: "not a pvr texture" })
+    }
+}
+
+fn expand(value: u16, bits: u32) -> u8 {
+    let max = (1u32 << bits) - 1;
+    ((value as u32 * 255 + max / 2) / max) as u8
+}
+
+/// Undo premultiplied alpha, sprites are blended with straight alpha.
+fn unpremultiply(rgba: &mut [u8]) {
+    for p in rgba.chunks_exact_mut(4) {
+        let a = p[3] as u32;
+        if a != 0 && a != 255 {
+            for c in &mut p[..3] {
+                *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+}
+
+/// Decode the first mip level into RGBA8 with straight alpha.
+fn decode_pvr(data: &[u8], path: &Path) -> Result<(u32, u32, Vec<u8>), CczTextureLoaderError> {
+    let PvrHeader { width, height, format, offset, premultiplied } = parse_pvr_header(data, path)?;
+    let len = width as usize * height as usize * format.pixel_size();
+    let pixels = data.get(offset..offset.saturating_add(len))
+        .ok_or_else(|| CczTextureLoaderError::BadHeader { path: path.to_path_buf(), reason: "truncated pvr pixel data" })?;
+
+    let mut rgba: Vec<u8> = match format {
+        PvrPixelFormat::Rgba8888 => pixels.to_vec(),
+        PvrPixelFormat::Bgra8888 => pixels.chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect(),
+        PvrPixelFormat::Rgba4444 => pixels.chunks_exact(2)
+            .map(|p| u16::from_le_bytes([p[0], p[1]]))
+            .flat_map(|v| [expand(v >> 12, 4), expand((v >> 8) & 0xf, 4), expand((v >> 4) & 0xf, 4), expand(v & 0xf, 4)])
+            .collect(),
+        PvrPixelFormat::Rgb565 => pixels.chunks_exact(2)
+            .map(|p| u16::from_le_bytes([p[0], p[1]]))
+            .flat_map(|v| [expand(v >> 11, 5), expand((v >> 5) & 0x3f, 6), expand(v & 0x1f, 5), 255])
+            .collect(),
+    };
+    if premultiplied {
+        unpremultiply(&mut rgba);
+    }
+
+    Ok((width, height, rgba))
+}
+
+impl AssetLoader for CczTextureLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = CczTextureLoaderError;
+
+    fn load<'a>(&'a self, reader: &'a mut Reader,
+                _settings: &'a Self::Settings, load_context: &'a mut LoadContext)
+                -> BoxedFuture<'a, Result<Self::Asset, Self::Error>>
+    {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let path = load_context.path();
+            let data = decompress_ccz(&bytes, path)?;
+            let (width, height, rgba) = decode_pvr(&data, path)?;
+
+            Ok(Image::new(
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                rgba,
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::default(),
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pvr.ccz", "ccz"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+
+    use super::*;
+
+    fn path() -> &'static Path {
+        Path::new("test.pvr.ccz")
+    }
+
+    fn pvr2(width: u32, height: u32, format: u32, pixels: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; PVR_HEADER_LEN];
+        data[0..4].copy_from_slice(&(PVR_HEADER_LEN as u32).to_le_bytes());
+        data[4..8].copy_from_slice(&height.to_le_bytes());
+        data[8..12].copy_from_slice(&width.to_le_bytes());
+        data[16..20].copy_from_slice(&format.to_le_bytes());
+        data[20..24].copy_from_slice(&(pixels.len() as u32).to_le_bytes());
+        data[44..48].copy_from_slice(b"PVR!");
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    fn pvr3(width: u32, height: u32, flags: u32, format: u64, metadata: &[u8], pixels: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; PVR_HEADER_LEN];
+        data[0..4].copy_from_slice(&PVR3_VERSION.to_le_bytes());
+        data[4..8].copy_from_slice(&flags.to_le_bytes());
+        data[8..16].copy_from_slice(&format.to_le_bytes());
+        data[24..28].copy_from_slice(&height.to_le_bytes());
+        data[28..32].copy_from_slice(&width.to_le_bytes());
+        data[48..52].copy_from_slice(&(metadata.len() as u32).to_le_bytes());
+        data.extend_from_slice(metadata);
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    fn ccz(data: &[u8], len: u32) -> Vec<u8> {
+        let mut bytes = b"CCZ!".to_vec();
+        bytes.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&len.to_be_bytes());
+
+        let mut encoder = ZlibEncoder::new(bytes, Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    const RGBA8888: [u8; 8] = [255, 0, 0, 255, 10, 20, 30, 40];
+    // 0xf00f: red, opaque, 0x0800: half green, transparent
+    const RGBA4444: [u8; 4] = [0x0f, 0xf0, 0x00, 0x08];
+    // 0xf800: red, 0x07e0: green, 0x001f: blue
+    const RGB565: [u8; 6] = [0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00];
+
+    #[test]
+    fn decode_pvr2() {
+        let (width, height, rgba) = decode_pvr(&pvr2(2, 1, 0x12, &RGBA8888), path()).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(rgba, RGBA8888);
+
+        let (_, _, rgba) = decode_pvr(&pvr2(1, 2, 0x10, &RGBA4444), path()).unwrap();
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 136, 0, 0]);
+
+        let (width, height, rgba) = decode_pvr(&pvr2(3, 1, 0x13, &RGB565), path()).unwrap();
+        assert_eq!((width, height), (3, 1));
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn decode_pvr3() {
+        let (width, height, rgba) = decode_pvr(&pvr3(1, 2, 0, 0x0808_0808_6162_6772, &[], &RGBA8888), path()).unwrap();
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(rgba, RGBA8888);
+
+        // the pixels start after the metadata
+        let (_, _, rgba) = decode_pvr(&pvr3(2, 1, 0, 0x0404_0404_6162_6772, &[7; 12], &RGBA4444), path()).unwrap();
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 136, 0, 0]);
+
+        let (_, _, rgba) = decode_pvr(&pvr3(3, 1, 0, 0x0005_0605_0062_6772, &[], &RGB565), path()).unwrap();
+        assert_eq!(rgba, [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn decode_premultiplied_pvr3() {
+        let pixels = [64, 32, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255];
+        let (_, _, rgba) = decode_pvr(&pvr3(3, 1, PVR3_PREMULTIPLIED, 0x0808_0808_6162_6772, &[], &pixels), path()).unwrap();
+        assert_eq!(rgba, [128, 64, 0, 128, 0, 0, 0, 0, 200, 100, 50, 255]);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let err = decode_pvr(&pvr2(4, 4, 0x12, &RGBA8888), path()).unwrap_err();
+        assert!(err.to_string().contains("truncated pvr pixel data"), "{}", err);
+
+        let err = decode_pvr(&pvr2(1, 1, 0x0c, &[0; 4]), path()).unwrap_err();
+        assert!(matches!(err, CczTextureLoaderError::UnsupportedFormat { .. }), "{}", err);
+
+        let err = decode_pvr(&[0; 60], path()).unwrap_err();
+        assert!(err.to_string().contains("not a pvr texture"), "{}", err);
+    }
+
+    #[test]
+    fn decompress() {
+        let data = pvr2(2, 1, 0x12, &RGBA8888);
+        assert_eq!(decompress_ccz(&ccz(&data, data.len() as u32), path()).unwrap(), data);
+
+        let err = decompress_ccz(&ccz(&data, data.len() as u32 - 1), path()).unwrap_err();
+        assert!(err.to_string().contains("doesn't match the header"), "{}", err);
+
+        // a huge length in the header fails on the data, not on the allocation
+        let err = decompress_ccz(&ccz(&data, u32::MAX), path()).unwrap_err();
+        assert!(err.to_string().contains("doesn't match the header"), "{}", err);
+
+        let mut encrypted = ccz(&data, data.len() as u32);
+        encrypted[..4].copy_from_slice(b"CCZp");
+        let err = decompress_ccz(&encrypted, path()).unwrap_err();
+        assert!(err.to_string().contains("encrypted"), "{}", err);
+    }
+}
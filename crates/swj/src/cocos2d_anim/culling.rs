@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::cocos2d_anim::Cocos2dAnimatorPlayer;
+
+/// Animators out of the camera view keep playing and sending events,
+/// but their layers are not updated.
+#[derive(Resource, Debug)]
+pub struct Cocos2dAnimCulling {
+    pub enabled: bool,
+    /// grow the camera view by this much before culling, in world units
+    pub margin: f32,
+    pub lod: Option<Cocos2dAnimLod>,
+}
+
+impl Default for Cocos2dAnimCulling {
+    fn default() -> Self {
+        Cocos2dAnimCulling {
+            enabled: true,
+            margin: 128.0,
+            lod: None,
+        }
+    }
+}
+
+/// Update the layers of low detail animators only every `stride` frames.
+#[derive(Debug, Clone)]
+pub struct Cocos2dAnimLod {
+    /// every animator is low detail once the camera projection scale reaches this
+    pub zoom: f32,
+    /// animators further than this from the view center are low detail
+    pub distance: f32,
+    pub stride: u32,
+}
+
+pub(crate) fn cull_animators(
+    settings: Res<Cocos2dAnimCulling>,
+    cameras: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut query: Query<(&GlobalTransform, &mut Cocos2dAnimatorPlayer)>,
+) {
+    let view = cameras.iter()
+        .find(|(camera, _, _)| camera.is_active)
+        .map(|(_, transform, projection)| {
+            let center = transform.translation().truncate();
+            let area = Rect::from_corners(projection.area.min + center, projection.area.max + center);
+            (center, area.inflate(settings.margin), projection.scale)
+        });
+
+    for (transform, mut player) in &mut query {
+        let (culled, stride) = match (&view, settings.enabled) {
+            (Some((center, area, scale)), true) => {
+                let pos = transform.translation().truncate();
+                let low_detail = settings.lod.as_ref().is_some_and(|lod| {
+                    *scale >= lod.zoom || pos.distance(*center) >= lod.distance
+                });
+                let stride = match &settings.lod {
+                    Some(lod) if low_detail => lod.stride.max(1),
+                    _ => 1,
+                };
+
+                (!area.contains(pos), stride)
+            }
+            _ => (false, 1),
+        };
+
+        if player.culled != culled || player.lod_stride != stride {
+            player.culled = culled;
+            player.lod_stride = stride;
+        }
+    }
+}
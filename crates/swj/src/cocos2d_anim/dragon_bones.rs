@@ -0,0 +1,891 @@
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::{
+    asset::{AssetLoader, io::Reader, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy::asset::AsyncReadExt;
+use bevy::utils::{HashMap, HashSet};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::cocos2d_anim::anim::{BoneData, Cocos2dAnimAsset, Cocos2dAnimBone, Cocos2dAnimFrame, Cocos2dAnimMove, display_offset, DisplayData, FrameEvent, new_display, sort_bones, TextureData};
+use crate::cocos2d_anim::event::FrameEventRegistry;
+use crate::cocos2d_anim::json::{JsonError, JsonErrorReason, JsonNode};
+use crate::cocos2d_anim::sprite_sheet::{atlas_layout, PlistSpriteFrameAsset, sheet_asset, SpriteFrame};
+use crate::cocos2d_anim::tween::TweenEasing;
+
+/// Key of the atlas in `TextureData::plist_file`, like a `config_file_path` entry.
+const SHEET_KEY: &str = "tex";
+/// Label of the skeleton's own copy of the atlas layout, an armature with this name
+/// is labeled `<name>_armature`.
+const LAYOUT_LABEL: &str = "atlas";
+
+/// Loads DragonBones 5.5+ armatures, `hero.ske.json` with its atlas `hero.tex.json` next to it,
+/// into the same [`Cocos2dAnimAsset`] as ExportJson files. Bevy picks loaders by the extension
+/// after the first dot, so rename the exported `hero_ske.json` and `hero_tex.json`, a plain
+/// `.json` goes to the config loader. Reach other armatures through [`Cocos2dAnimAsset::armature`]
+/// instead of a labeled path.
+///
+/// Bones keep their names. Every slot becomes a child bone of its DragonBones bone named after
+/// the slot and holding its displays, a bone named like a slot is renamed to `<name>_bone`.
+/// Translate, rotate and scale timelines are merged into one keyframe list per bone.
+/// `zOrder` timelines, meshes, IK constraints and child armatures are not supported.
+#[derive(Default)]
+pub struct DragonBonesAssetLoader {
+    pub events: Arc<FrameEventRegistry>,
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum DragonBonesLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{}: invalid json: {source}", .path.display())]
+    Syntax {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A missing or malformed field
+    #[error(transparent)]
+    Field(#[from] JsonError),
+    #[error("{}: can't read texture atlas {atlas}: {reason}", .path.display())]
+    TextureAtlas {
+        path: PathBuf,
+        atlas: String,
+        reason: String,
+    },
+    #[error("{}: DragonBones data version {version} is not supported, export 5.5 or newer", .path.display())]
+    Version {
+        path: PathBuf,
+        version: String,
+    },
+    #[error("{}: {pointer}: unknown bone {name}", .path.display())]
+    UnknownBone {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+    },
+    #[error("{}: {pointer}: unknown slot {name}", .path.display())]
+    UnknownSlot {
+        path: PathBuf,
+        pointer: String,
+        name: String,
+    },
+    #[error("{}: armature is empty", .path.display())]
+    NoArmature {
+        path: PathBuf,
+    },
+    #[error("{}: armature {name} is defined twice", .path.display())]
+    DuplicateArmature {
+        path: PathBuf,
+        name: String,
+    },
+}
+
+/// `hero.ske.json` -> `hero.tex.json`
+fn atlas_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = file_name.strip_suffix(".ske.json")
+        .or_else(|| file_name.strip_suffix(".json"))
+        .unwrap_or(&file_name);
+
+    path.with_file_name(format!("{}.tex.json", stem))
+}
+
+fn parse_atlas(root: &JsonNode) -> Result<(Vec<SpriteFrame>, String, Vec2), JsonError> {
+    let mut frames = vec![];
+
+    for sub in root.get("SubTexture")?.items()? {
+        let w = sub.get("width")?.f32()?;
+        let h = sub.get("height")?.f32()?;
+        let rotated = match sub.opt("rotated") {
+            Some(rotated) => rotated.bool()?,
+            None => false,
+        };
+        // untrimmed images have no frame rect
+        let trim = (-sub.f32_or("frameX", 0.0)?, -sub.f32_or("frameY", 0.0)?);
+        let source_size = (sub.f32_or("frameWidth", w)?, sub.f32_or("frameHeight", h)?);
+
+        frames.push(SpriteFrame {
+            name: sub.get("name")?.str()?.to_string(),
+            frame: (sub.get("x")?.f32()?, sub.get("y")?.f32()?, w, h),
+            offset: (
+                trim.0 + w / 2.0 - source_size.0 / 2.0,
+                source_size.1 / 2.0 - (trim.1 + h / 2.0),
+            ),
+            rotated,
+            source_color_rect: (trim.0, trim.1, w, h),
+            source_size,
+            pivot: None,
+        });
+    }
+
+    let image = root.get("imagePath")?.str()?.to_string();
+    let dims = Vec2::new(root.get("width")?.f32()?, root.get("height")?.f32()?);
+
+    Ok((frames, image, dims))
+}
+
+/// A DragonBones `transform`, y down in clockwise degrees, as cocos translate, scale and skew.
+fn parse_transform(node: Option<JsonNode>) -> Result<(Vec2, Vec2, Vec2), JsonError> {
+    let Some(t) = node else {
+        return Ok((Vec2::ZERO, Vec2::ONE, Vec2::ZERO));
+    };
+
+    Ok((
+        Vec2::new(t.f32_or("x", 0.0)?, -t.f32_or("y", 0.0)?),
+        Vec2::new(t.f32_or("scX", 1.0)?, t.f32_or("scY", 1.0)?),
+        rotate_skew(t.f32_or("skX", 0.0)?, t.f32_or("skY", 0.0)?),
+    ))
+}
+
+/// Clockwise skew degrees in a y down space to cocos skew radians.
+fn rotate_skew(sk_x: f32, sk_y: f32) -> Vec2 {
+    Vec2::new(sk_x, -sk_y) * (PI / 180.0)
+}
+
+/// Color multipliers in percent, color offsets are ignored.
+fn parse_color(node: Option<JsonNode>) -> Result<Vec4, JsonError> {
+    let Some(c) = node else {
+        return Ok(Vec4::ONE);
+    };
+
+    Ok(Vec4::new(
+        c.f32_or("rM", 100.0)?,
+        c.f32_or("gM", 100.0)?,
+        c.f32_or("bM", 100.0)?,
+        c.f32_or("aM", 100.0)?,
+    ) / 100.0)
+}
+
+fn to_color(c: Vec4) -> Color {
+    Color::srgba(c.x, c.y, c.z, c.w)
+}
+
+/// `tweenEasing` of a keyframe, a missing value holds the keyframe.
+fn parse_easing(frame: &JsonNode) -> Result<Option<TweenEasing>, JsonError> {
+    // bezier curves are not supported, fall back to linear like cocos does
+    if frame.opt("curve").is_some() {
+        return Ok(Some(TweenEasing::Linear));
+    }
+
+    let easing = match frame.opt("tweenEasing") {
+        Some(easing) if !easing.value().is_null() => easing.f32()?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(match easing {
+        0.0 => TweenEasing::Linear,
+        e if e < 0.0 => TweenEasing::QuadIn,
+        e if e <= 1.0 => TweenEasing::QuadOut,
+        _ => TweenEasing::QuadInOut,
+    }))
+}
+
+struct Key<T> {
+    fi: usize,
+    value: T,
+    tween: Option<TweenEasing>,
+}
+
+fn parse_timeline<T>(node: Option<JsonNode>, value: impl Fn(&JsonNode) -> Result<T, JsonError>) -> Result<Vec<Key<T>>, JsonError> {
+    let mut keys = vec![];
+    let mut fi = 0;
+
+    if let Some(node) = node {
+        for frame in node.items()? {
+            keys.push(Key {
+                fi,
+                value: value(&frame)?,
+                tween: parse_easing(&frame)?,
+            });
+            fi += frame.f32_or("duration", 1.0)?.max(0.0) as usize;
+        }
+    }
+
+    Ok(keys)
+}
+
+/// The timeline value at `fi`, `None` before its first key.
+fn sample<T: Copy>(keys: &[Key<T>], fi: usize, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let idx = keys.partition_point(|k| k.fi <= fi).checked_sub(1)?;
+    let key = &keys[idx];
+
+    match (key.tween, keys.get(idx + 1)) {
+        (Some(easing), Some(next)) if next.fi > key.fi => {
+            let t = easing.ease((fi - key.fi) as f32 / (next.fi - key.fi) as f32);
+            Some(lerp(key.value, next.value, t))
+        }
+        _ => Some(key.value),
+    }
+}
+
+/// Whether the timeline has a key at `fi` and the easing of the segment `fi` is in.
+fn segment<T>(keys: &[Key<T>], fi: usize) -> Option<(bool, Option<TweenEasing>)> {
+    let idx = keys.partition_point(|k| k.fi <= fi).checked_sub(1)?;
+    let key = &keys[idx];
+    let tween = key.tween.filter(|_| idx + 1 < keys.len());
+
+    Some((key.fi == fi, tween))
+}
+
+/// Easing from the merged keyframe at `fi` to the next one. Timelines that tween from a key
+/// at `fi` keep their easing if they agree, anything else is approximated linearly.
+fn merged_tween(segments: &[Option<(bool, Option<TweenEasing>)>]) -> Option<TweenEasing> {
+    let mut tweens = segments.iter()
+        .flatten()
+        .filter_map(|(starts, tween)| tween.map(|tween| (*starts, tween)));
+    let (starts, first) = tweens.next()?;
+
+    if starts && tweens.all(|(s, t)| s && t == first) {
+        Some(first)
+    } else {
+        Some(TweenEasing::Linear)
+    }
+}
+
+fn key_times<T>(keys: &[Key<T>]) -> impl Iterator<Item=usize> + '_ {
+    keys.iter().map(|k| k.fi)
+}
+
+/// The last frame of every held key before the value changes. A merged keyframe there keeps
+/// the step when other timelines tween across it, their tween would smooth the held value.
+fn hold_ends<T: PartialEq>(keys: &[Key<T>]) -> impl Iterator<Item=usize> + '_ {
+    keys.windows(2)
+        .filter(|k| k[0].tween.is_none() && k[0].value != k[1].value && k[1].fi > k[0].fi + 1)
+        .map(|k| k[1].fi - 1)
+}
+
+struct Slot {
+    name: String,
+    bone: String,
+    display_index: Option<usize>,
+    color: Vec4,
+    /// `None` for display types other than images
+    displays: Vec<Option<DisplayData>>,
+}
+
+struct ArmatureEvent {
+    fi: usize,
+    bone: String,
+    name: String,
+    node: Value,
+    pointer: String,
+}
+
+/// `unknown_events` holds the unregistered names already reported for this file.
+fn parse_event(evt: &ArmatureEvent, translate: Vec2, events: &FrameEventRegistry,
+               unknown_events: &mut HashSet<String>, path: &Path) -> Result<FrameEvent, JsonError> {
+    match events.parse(&evt.name, translate, &evt.node) {
+        Some(Ok(parsed)) => Ok(parsed),
+        Some(Err(reason)) => Err(JsonError {
+            path: path.to_path_buf(),
+            pointer: evt.pointer.clone(),
+            reason: JsonErrorReason::BadValue(reason),
+        }),
+        None => {
+            if unknown_events.insert(evt.name.clone()) {
+                warn!("{}: {}: frame event {} is not registered, later uses in this file are not reported", path.display(), evt.pointer, evt.name);
+            }
+            Ok(FrameEvent::Other(evt.name.clone()))
+        }
+    }
+}
+
+/// A keyframe that moves the bone but shows nothing.
+fn bone_frame(translate: Vec3, scale: Vec2, skew: Vec2, fi: usize) -> Cocos2dAnimFrame {
+    Cocos2dAnimFrame {
+        translate,
+        scale,
+        skew,
+        display_offset: Vec3::ZERO,
+        display_scale: Vec2::ONE,
+        display_skew: Vec2::ZERO,
+        rotated: false,
+        visible: false,
+        di: None,
+        evt: None,
+        color: None,
+        tween: None,
+        fi,
+        sprite_atlas: Handle::default(),
+        texture: Handle::default(),
+        sprite_idx: 0,
+    }
+}
+
+fn parse_armature(armature: &JsonNode,
+                  frame_rate: f32,
+                  sheet: &PlistSpriteFrameAsset,
+                  sheet_handle: &Handle<PlistSpriteFrameAsset>,
+                  events: &FrameEventRegistry,
+                  unknown_events: &mut HashSet<String>,
+                  path: &Path)
+                  -> Result<(HashMap<String, Cocos2dAnimMove>, Vec<Cocos2dAnimBone>), DragonBonesLoaderError> {
+    let frame_rate = armature.f32_or("frameRate", frame_rate)?.max(1.0);
+
+    let mut slots = Vec::new();
+    for slot in armature.opt("slot").map_or(Ok(Vec::new()), |s| s.items())? {
+        let display_index = slot.f32_or("displayIndex", 0.0)?;
+        slots.push(Slot {
+            name: slot.get("name")?.str()?.to_string(),
+            bone: slot.get("parent")?.str()?.to_string(),
+            display_index: (display_index >= 0.0).then_some(display_index as usize),
+            color: parse_color(slot.opt("color"))?,
+            displays: Vec::new(),
+        });
+    }
+    let slot_names: HashSet<String> = slots.iter().map(|s| s.name.clone()).collect();
+
+    // DragonBones bone name -> bone name in the asset
+    let mut bone_names = HashMap::new();
+    let mut names = Vec::new();
+    let mut bone_data = HashMap::new();
+    for bone in armature.get("bone")?.items()? {
+        let db_name = bone.get("name")?.str()?.to_string();
+        let name = if slot_names.contains(&db_name) {
+            format!("{}_bone", db_name)
+        } else {
+            db_name.clone()
+        };
+        let parent = match bone.opt("parent") {
+            Some(parent) => Some(parent.str()?.to_string()),
+            None => None,
+        };
+        let (translate, scale, skew) = parse_transform(bone.opt("transform"))?;
+
+        bone_names.insert(db_name, name.clone());
+        names.push(name.clone());
+        bone_data.insert(name, BoneData {
+            parent,
+            display_data: Vec::new(),
+            translate: translate.extend(0.0),
+            scale,
+            skew,
+        });
+    }
+    if names.is_empty() {
+        return Err(DragonBonesLoaderError::NoArmature { path: path.to_path_buf() });
+    }
+
+    for data in bone_data.values_mut() {
+        data.parent = data.parent.as_ref().map(|p| bone_names.get(p).cloned().unwrap_or_else(|| p.clone()));
+    }
+
+    let skins = armature.opt("skin").map_or(Ok(Vec::new()), |s| s.items())?;
+    if skins.len() > 1 {
+        warn!("{}: {}: only the first of {} skins is used", path.display(), armature.get("skin")?.pointer(), skins.len());
+    }
+    for skin in skins.iter().take(1) {
+        for skin_slot in skin.opt("slot").map_or(Ok(Vec::new()), |s| s.items())? {
+            let name = skin_slot.get("name")?.str()?;
+            let Some(slot) = slots.iter_mut().find(|s| s.name == name) else {
+                return Err(DragonBonesLoaderError::UnknownSlot {
+                    path: path.to_path_buf(),
+                    pointer: skin_slot.pointer().to_string(),
+                    name: name.to_string(),
+                });
+            };
+
+            for display in skin_slot.get("display")?.items()? {
+                let display_type = display.opt("type").map_or(Ok("image"), |t| t.str())?;
+                if display_type != "image" {
+                    let pointer = display.pointer();
+                    warn!("{}: {}: {} displays are not supported", path.display(), pointer, display_type);
+                    slot.displays.push(None);
+                    continue;
+                }
+
+                let name = match display.opt("path") {
+                    Some(p) => p.str()?,
+                    None => display.get("name")?.str()?,
+                };
+                let (xy, scale, skew) = parse_transform(display.opt("transform"))?;
+                slot.displays.push(Some(DisplayData {
+                    name: name.to_string(),
+                    xy,
+                    scale,
+                    skew,
+                }));
+            }
+        }
+    }
+
+    // slots draw in the order they are listed
+    for (z, slot) in slots.iter().enumerate() {
+        let Some(bone) = bone_names.get(&slot.bone) else {
+            return Err(DragonBonesLoaderError::UnknownBone {
+                path: path.to_path_buf(),
+                pointer: armature.get("slot")?.pointer().to_string(),
+                name: slot.bone.clone(),
+            });
+        };
+
+        names.push(slot.name.clone());
+        bone_data.insert(slot.name.clone(), BoneData {
+            parent: Some(bone.clone()),
+            display_data: Vec::new(),
+            translate: Vec3::new(0.0, 0.0, z as f32),
+            scale: Vec2::ONE,
+            skew: Vec2::ZERO,
+        });
+    }
+
+    // every image shows with its center on the display transform
+    let texture_data: HashMap<String, TextureData> = sheet.frames.iter()
+        .map(|sf| (sf.name.clone(), TextureData {
+            plist_file: SHEET_KEY.to_string(),
+            px: 0.5,
+            py: 0.5,
+        }))
+        .collect();
+    let plist_handles: HashMap<String, Handle<PlistSpriteFrameAsset>> = [(SHEET_KEY.to_string(), sheet_handle.clone())]
+        .into_iter()
+        .collect();
+
+    let mut bones = sort_bones(&names, &bone_data, path);
+    for bone in bones.iter_mut() {
+        let Some(slot) = slots.iter().find(|s| s.name == bone.name) else {
+            continue;
+        };
+
+        bone.rest.color = to_color(slot.color);
+        // unsupported displays keep their index with an empty name
+        bone.displays = slot.displays.iter()
+            .map(|dd| match dd {
+                Some(dd) => new_display(dd, &texture_data, &plist_handles),
+                None => new_display(&DisplayData {
+                    name: String::new(),
+                    xy: Vec2::ZERO,
+                    scale: Vec2::ONE,
+                    skew: Vec2::ZERO,
+                }, &texture_data, &plist_handles),
+            })
+            .collect();
+    }
+
+    let mut animation = HashMap::new();
+    for anim in armature.opt("animation").map_or(Ok(Vec::new()), |a| a.items())? {
+        let anim_name = anim.get("name")?.str()?.to_string();
+        let duration = anim.f32_or("duration", 0.0)?.max(0.0) as usize;
+        let mut layers = HashMap::new();
+
+        let root_bone = names[0].clone();
+        let mut armature_events = Vec::new();
+        let mut fi = 0;
+        for frame in anim.opt("frame").map_or(Ok(Vec::new()), |f| f.items())? {
+            let frame_events = match frame.opt("events") {
+                Some(list) => list.items()?,
+                None => Vec::new(),
+            };
+            for evt in frame_events {
+                let bone = match evt.opt("bone") {
+                    Some(bone) => match bone_names.get(bone.str()?) {
+                        Some(name) => name.clone(),
+                        None => {
+                            return Err(DragonBonesLoaderError::UnknownBone {
+                                path: path.to_path_buf(),
+                                pointer: bone.pointer().to_string(),
+                                name: bone.str()?.to_string(),
+                            });
+                        }
+                    },
+                    None => root_bone.clone(),
+                };
+
+                armature_events.push(ArmatureEvent {
+                    fi,
+                    bone,
+                    name: evt.get("name")?.str()?.to_string(),
+                    node: evt.value().clone(),
+                    pointer: evt.pointer().to_string(),
+                });
+            }
+            fi += frame.f32_or("duration", 1.0)?.max(0.0) as usize;
+        }
+
+        let mut timelines = HashMap::new();
+        for timeline in anim.opt("bone").map_or(Ok(Vec::new()), |b| b.items())? {
+            let db_name = timeline.get("name")?.str()?;
+            let Some(name) = bone_names.get(db_name) else {
+                return Err(DragonBonesLoaderError::UnknownBone {
+                    path: path.to_path_buf(),
+                    pointer: timeline.pointer().to_string(),
+                    name: db_name.to_string(),
+                });
+            };
+            if timeline.opt("frame").is_some() {
+                warn!("{}: {}: the legacy combined bone timeline is ignored, export 5.5 or newer", path.display(), timeline.pointer());
+            }
+
+            let translate = parse_timeline(timeline.opt("translateFrame"), |f| {
+                Ok(Vec2::new(f.f32_or("x", 0.0)?, -f.f32_or("y", 0.0)?))
+            })?;
+            let rotate = parse_timeline(timeline.opt("rotateFrame"), |f| f.f32_or("rotate", 0.0))?;
+            let scale = parse_timeline(timeline.opt("scaleFrame"), |f| {
+                Ok(Vec2::new(f.f32_or("x", 1.0)?, f.f32_or("y", 1.0)?))
+            })?;
+
+            timelines.insert(name.clone(), (translate, rotate, scale));
+        }
+
+        let event_bones: HashSet<&String> = armature_events.iter().map(|e| &e.bone).collect();
+        for name in timelines.keys().chain(event_bones).collect::<HashSet<_>>() {
+            let empty = (Vec::new(), Vec::new(), Vec::new());
+            let (translate, rotate, scale) = timelines.get(name).unwrap_or(&empty);
+            let bd = &bone_data[name];
+
+            let mut times: Vec<usize> = key_times(translate)
+                .chain(key_times(rotate))
+                .chain(key_times(scale))
+                .chain(hold_ends(translate))
+                .chain(hold_ends(rotate))
+                .chain(hold_ends(scale))
+                .chain(armature_events.iter().filter(|e| e.bone == *name).map(|e| e.fi))
+                .chain([0])
+                .collect();
+            times.sort_unstable();
+            times.dedup();
+
+            let mut frames = Vec::new();
+            for fi in times {
+                let delta = sample(translate, fi, |a, b, t| a.lerp(b, t)).unwrap_or(Vec2::ZERO);
+                let rotation = sample(rotate, fi, |a, b, t| a + (b - a) * t).unwrap_or(0.0);
+                let scaling = sample(scale, fi, |a, b, t| a.lerp(b, t)).unwrap_or(Vec2::ONE);
+
+                let mut frame = bone_frame(bd.translate + delta.extend(0.0), bd.scale * scaling, bd.skew + rotate_skew(rotation, rotation), fi);
+                frame.tween = merged_tween(&[segment(translate, fi), segment(rotate, fi), segment(scale, fi)]);
+
+                for evt in armature_events.iter().filter(|e| e.bone == *name && e.fi == fi) {
+                    if frame.evt.is_some() {
+                        warn!("{}: {}: bone {} already has an event on frame {}, {} is dropped", path.display(), evt.pointer, name, fi, evt.name);
+                        continue;
+                    }
+                    frame.evt = Some(parse_event(evt, delta, events, unknown_events, path)?);
+                }
+
+                frames.push(frame);
+            }
+
+            layers.insert(name.clone(), frames);
+        }
+
+        let mut slot_timelines = HashMap::new();
+        for timeline in anim.opt("slot").map_or(Ok(Vec::new()), |s| s.items())? {
+            let name = timeline.get("name")?.str()?;
+            if !slot_names.contains(name) {
+                return Err(DragonBonesLoaderError::UnknownSlot {
+                    path: path.to_path_buf(),
+                    pointer: timeline.pointer().to_string(),
+                    name: name.to_string(),
+                });
+            }
+
+            let display = parse_timeline(timeline.opt("displayFrame"), |f| {
+                let value = f.f32_or("value", 0.0)?;
+                Ok((value >= 0.0).then_some(value as usize))
+            })?;
+            let color = parse_timeline(timeline.opt("colorFrame"), |f| parse_color(f.opt("value")))?;
+
+            slot_timelines.insert(name.to_string(), (display, color));
+        }
+
+        // every slot needs a layer to show its display
+        for slot in &slots {
+            let empty = (Vec::new(), Vec::new());
+            let (display, color) = slot_timelines.get(&slot.name).unwrap_or(&empty);
+            let Some(bone) = bones.iter().find(|b| b.name == slot.name) else {
+                return Err(DragonBonesLoaderError::UnknownSlot {
+                    path: path.to_path_buf(),
+                    pointer: armature.get("slot")?.pointer().to_string(),
+                    name: slot.name.clone(),
+                });
+            };
+
+            let mut times: Vec<usize> = key_times(display).chain(key_times(color)).chain([0]).collect();
+            times.sort_unstable();
+            times.dedup();
+
+            let mut frames = Vec::new();
+            for fi in times {
+                let di = sample(display, fi, |a, _, _| a).unwrap_or(slot.display_index);
+                let rgba = sample(color, fi, |a, b, t| a.lerp(b, t)).unwrap_or(slot.color);
+
+                let mut frame = bone_frame(bone.rest.translate, Vec2::ONE, Vec2::ZERO, fi);
+                frame.color = Some(to_color(rgba));
+                frame.tween = merged_tween(&[segment(color, fi)]);
+
+                let shown = di.and_then(|di| Some((di, slot.displays.get(di)?.as_ref()?)));
+                if let Some((di, dd)) = shown {
+                    match sheet.frame_index(&dd.name) {
+                        Some(sprite_idx) => {
+                            let sprite_frame = &sheet.frames[sprite_idx];
+                            frame.display_offset = display_offset(&bone.displays[di], sprite_frame);
+                            frame.display_scale = dd.scale;
+                            frame.display_skew = dd.skew;
+                            frame.rotated = sprite_frame.rotated;
+                            frame.visible = true;
+                            frame.di = Some(di);
+                            frame.sprite_atlas = sheet.atlas.clone();
+                            frame.texture = sheet.texture.clone();
+                            frame.sprite_idx = sprite_idx;
+                        }
+                        None => {
+                            warn!("{}: image {} of slot {} is not in the texture atlas", path.display(), dd.name, slot.name);
+                        }
+                    }
+                }
+
+                frames.push(frame);
+            }
+
+            layers.insert(slot.name.clone(), frames);
+        }
+
+        animation.insert(anim_name, Cocos2dAnimMove {
+            interval: 1.0 / frame_rate,
+            frame_size: duration + 1,
+            layers,
+        });
+    }
+
+    Ok((animation, bones))
+}
+
+/// Loads `hero.tex.json` as a sprite sheet, with the same labels as a plist sheet.
+#[derive(Default)]
+pub struct DragonBonesAtlasLoader;
+
+impl AssetLoader for DragonBonesAtlasLoader {
+    type Asset = PlistSpriteFrameAsset;
+    type Settings = ();
+    type Error = DragonBonesLoaderError;
+
+    fn load<'a>(&'a self, reader: &'a mut Reader,
+                _settings: &'a Self::Settings,
+                load_context: &'a mut LoadContext)
+                -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let path = load_context.path().to_path_buf();
+            let tex: Value = serde_json::from_slice(&bytes)
+                .map_err(|source| DragonBonesLoaderError::Syntax { path: path.clone(), source })?;
+            let (frames, image, dims) = parse_atlas(&JsonNode::root(&path, &tex))?;
+
+            Ok(sheet_asset(load_context, frames, &image, dims))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tex.json"]
+    }
+}
+
+impl AssetLoader for DragonBonesAssetLoader {
+    type Asset = Cocos2dAnimAsset;
+    type Settings = ();
+    type Error = DragonBonesLoaderError;
+
+    fn load<'a>(&'a self, reader: &'a mut Reader,
+                _settings: &'a Self::Settings,
+                load_context: &'a mut LoadContext)
+                -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let path = load_context.path().to_path_buf();
+            let ske: Value = serde_json::from_slice(&bytes)
+                .map_err(|source| DragonBonesLoaderError::Syntax { path: path.clone(), source })?;
+            let ske = JsonNode::root(&path, &ske);
+
+            let version = ske.opt("version").map_or(Ok("5.5"), |v| v.str())?;
+            let mut numbers = version.split('.').map(|n| n.parse::<u32>().unwrap_or(0));
+            let (major, minor) = (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0));
+            if (major, minor) < (5, 5) {
+                return Err(DragonBonesLoaderError::Version { path: path.clone(), version: version.to_string() });
+            }
+
+            // the atlas is an asset of its own, its frames are read here again for the baked keyframes
+            let tex_path = atlas_path(&path);
+            let tex_bytes = load_context.read_asset_bytes(tex_path.clone()).await
+                .map_err(|e| DragonBonesLoaderError::TextureAtlas {
+                    path: path.clone(),
+                    atlas: tex_path.display().to_string(),
+                    reason: e.to_string(),
+                })?;
+            let tex: Value = serde_json::from_slice(&tex_bytes)
+                .map_err(|source| DragonBonesLoaderError::Syntax { path: tex_path.clone(), source })?;
+            let (frames, image, dims) = parse_atlas(&JsonNode::root(&tex_path, &tex))?;
+
+            let sheet_handle: Handle<PlistSpriteFrameAsset> = load_context.load(tex_path.clone());
+            let texture = load_context.load(tex_path.with_file_name(image));
+            let layout = load_context.add_labeled_asset(LAYOUT_LABEL.to_string(), atlas_layout(&frames, dims));
+            let sheet = PlistSpriteFrameAsset::new(frames, layout, texture);
+
+            let frame_rate = ske.f32_or("frameRate", 24.0)?;
+            let mut root = None;
+            let mut armatures = HashMap::new();
+            let mut unknown_events = HashSet::new();
+
+            for armature in ske.get("armature")?.items()? {
+                let name = armature.get("name")?.str()?.to_string();
+                if armatures.contains_key(&name) {
+                    return Err(DragonBonesLoaderError::DuplicateArmature { path: path.clone(), name });
+                }
+                let (animation, bones) = parse_armature(&armature, frame_rate, &sheet, &sheet_handle, &self.events, &mut unknown_events, &path)?;
+
+                let asset = Cocos2dAnimAsset {
+                    name: name.clone(),
+                    animation,
+                    bones,
+                    armatures: HashMap::new(),
+                    plist_handles: vec![sheet_handle.clone()],
+                };
+
+                if root.is_none() {
+                    root = Some(asset.clone());
+                }
+
+                let label = if name == LAYOUT_LABEL {
+                    format!("{}_armature", name)
+                } else {
+                    name.clone()
+                };
+                let handle = load_context.add_labeled_asset(label, asset);
+                armatures.insert(name, handle);
+            }
+
+            let mut root = root.ok_or(DragonBonesLoaderError::NoArmature { path: path.clone() })?;
+            root.armatures = armatures;
+
+            Ok(root)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ske.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn testdata(name: &str) -> (PathBuf, Value) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/dragon_bones").join(name);
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        (path, serde_json::from_str(&text).unwrap())
+    }
+
+    fn hero() -> Cocos2dAnimAsset {
+        let (tex_path, tex) = testdata("hero.tex.json");
+        let (frames, image, _) = parse_atlas(&JsonNode::root(&tex_path, &tex)).unwrap();
+        assert_eq!(image, "hero_tex.png");
+        let sheet = PlistSpriteFrameAsset::new(frames, Handle::default(), Handle::default());
+
+        let (path, ske) = testdata("hero.ske.json");
+        let ske = JsonNode::root(&path, &ske);
+        let armature = &ske.get("armature").unwrap().items().unwrap()[0];
+        let (animation, bones) = parse_armature(armature, 30.0, &sheet, &Handle::default(), &FrameEventRegistry::default(), &mut HashSet::new(), &path)
+            .unwrap();
+
+        Cocos2dAnimAsset {
+            name: "hero".to_string(),
+            animation,
+            bones,
+            armatures: HashMap::new(),
+            plist_handles: Vec::new(),
+        }
+    }
+
+    fn parent_name(asset: &Cocos2dAnimAsset, bone: &str) -> Option<String> {
+        let parent = asset.bones[asset.bone_idx(bone)?].parent?;
+        Some(asset.bones[parent].name.clone())
+    }
+
+    #[test]
+    fn bones_and_slots() {
+        let hero = hero();
+        let names: Vec<&str> = hero.bones.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["root", "body_bone", "arm", "body", "weapon"]);
+
+        assert_eq!(parent_name(&hero, "root"), None);
+        assert_eq!(parent_name(&hero, "body_bone").as_deref(), Some("root"));
+        assert_eq!(parent_name(&hero, "arm").as_deref(), Some("body_bone"));
+        assert_eq!(parent_name(&hero, "body").as_deref(), Some("body_bone"));
+        assert_eq!(parent_name(&hero, "weapon").as_deref(), Some("arm"));
+
+        // y down to y up, clockwise to counterclockwise
+        let arm = &hero.bones[2];
+        assert_eq!(hero.bones[1].rest.translate, Vec3::new(0.0, 20.0, 0.0));
+        assert_eq!(arm.rest.translate, Vec3::new(10.0, 30.0, 0.0));
+        assert!(arm.rest.skew.abs_diff_eq(Vec2::new(FRAC_PI_2, -FRAC_PI_2), 1e-6));
+
+        // slots hold the displays and draw in listed order
+        let weapon = &hero.bones[4];
+        assert_eq!(weapon.displays.len(), 1);
+        assert_eq!(weapon.displays[0].name, "sword");
+        assert_eq!(weapon.displays[0].xy, Vec2::new(5.0, 0.0));
+        assert_eq!(weapon.rest.translate.z, 1.0);
+        assert_eq!(weapon.rest.color, Color::srgba(1.0, 1.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn merged_bone_keys() {
+        let hero = hero();
+        let attack = &hero.animation["attack"];
+        assert_eq!(attack.frame_size, 11);
+        assert_eq!(attack.interval, 1.0 / 24.0);
+
+        let frames = &attack.layers["arm"];
+        let times: Vec<usize> = frames.iter().map(|f| f.fi).collect();
+        // 3 ends the held rotation before it steps to 30 while the translation tweens on
+        assert_eq!(times, [0, 3, 4, 5, 6]);
+
+        let rest_skew = Vec2::new(FRAC_PI_2, -FRAC_PI_2);
+        assert!(frames[1].translate.abs_diff_eq(Vec3::new(16.0, 24.0, 0.0), 1e-4));
+        assert!(frames[1].skew.abs_diff_eq(rest_skew, 1e-6));
+        assert!(frames[2].skew.abs_diff_eq(rest_skew + Vec2::new(1.0, -1.0) * 30f32.to_radians(), 1e-6));
+        assert!(frames[3].translate.abs_diff_eq(Vec3::new(20.0, 20.0, 0.0), 1e-4));
+
+        let tweens: Vec<Option<TweenEasing>> = frames.iter().map(|f| f.tween).collect();
+        assert_eq!(tweens, [Some(TweenEasing::Linear), Some(TweenEasing::Linear), Some(TweenEasing::Linear), None, None]);
+    }
+
+    #[test]
+    fn display_frame_minus_one_hides_slot() {
+        let hero = hero();
+        let frames = &hero.animation["attack"].layers["weapon"];
+
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].visible);
+        assert_eq!(frames[0].di, Some(0));
+        assert_eq!(frames[1].fi, 6);
+        assert!(!frames[1].visible);
+        assert_eq!(frames[1].di, None);
+
+        let body = &hero.animation["attack"].layers["body"];
+        assert!(body[0].visible);
+    }
+
+    #[test]
+    fn event_on_bone_frame() {
+        let hero = hero();
+        let attack = &hero.animation["attack"];
+
+        let frame = attack.layers["arm"].iter().find(|f| f.fi == 6).unwrap();
+        assert!(matches!(frame.evt, Some(FrameEvent::Perform)));
+        assert!(matches!(hero.events_at(attack, 6).collect::<Vec<_>>()[..], [FrameEvent::Perform]));
+        assert_eq!(hero.events_at(attack, 5).count(), 0);
+    }
+}
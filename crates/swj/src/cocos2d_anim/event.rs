@@ -0,0 +1,117 @@
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde_json::Value;
+
+use crate::cocos2d_anim::anim::FrameEvent;
+
+/// What a parser gets from a keyframe with `"evt": "name#arg"`.
+pub struct FrameEventInput<'a> {
+    pub name: &'a str,
+    /// the text after `#`, `None` if the event has no `#`
+    pub arg: Option<&'a str>,
+    /// bone position of the keyframe
+    pub translate: Vec2,
+    /// the whole keyframe json, for payloads stored in extra fields
+    pub frame: &'a Value,
+}
+
+/// A registered event with its parsed payload.
+#[derive(Clone)]
+pub struct TypedFrameEvent {
+    pub name: String,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl TypedFrameEvent {
+    pub fn payload<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+}
+
+impl Debug for TypedFrameEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TypedFrameEvent({})", self.name)
+    }
+}
+
+type FrameEventParser = Arc<dyn Fn(&FrameEventInput) -> Result<FrameEvent, String> + Send + Sync>;
+
+/// Frame event names known to the ExportJson loader. `perform` is always registered.
+#[derive(Clone)]
+pub struct FrameEventRegistry {
+    parsers: HashMap<String, FrameEventParser>,
+}
+
+impl Default for FrameEventRegistry {
+    fn default() -> Self {
+        let mut registry = FrameEventRegistry {
+            parsers: HashMap::new(),
+        };
+
+        registry.parsers.insert("perform".to_string(), Arc::new(parse_perform));
+        registry
+    }
+}
+
+impl FrameEventRegistry {
+    /// Register `name`, its payload shows up as [`FrameEvent::Typed`].
+    pub fn register<T: Any + Send + Sync>(
+        &mut self,
+        name: &str,
+        parser: impl Fn(&FrameEventInput) -> Result<T, String> + Send + Sync + 'static,
+    ) {
+        let event_name = name.to_string();
+        self.parsers.insert(name.to_string(), Arc::new(move |input| {
+            let payload = parser(input)?;
+            Ok(FrameEvent::Typed(TypedFrameEvent {
+                name: event_name.clone(),
+                payload: Arc::new(payload),
+            }))
+        }));
+    }
+
+    /// `None` if no parser is registered for the event.
+    pub(crate) fn parse(&self, evt: &str, translate: Vec2, frame: &Value) -> Option<Result<FrameEvent, String>> {
+        let (name, arg) = match evt.split_once('#') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (evt, None),
+        };
+
+        let parser = self.parsers.get(name)?;
+        Some(parser(&FrameEventInput {
+            name,
+            arg,
+            translate,
+            frame,
+        }))
+    }
+}
+
+/// `perform` hits the target, `perform#` hits the bone position moved by `perform_offset`.
+fn parse_perform(input: &FrameEventInput) -> Result<FrameEvent, String> {
+    if input.arg.is_none() {
+        return Ok(FrameEvent::Perform);
+    }
+
+    let perform_offset = if let Some(offset) = input.frame.get("perform_offset") {
+        let offset_str = offset.as_str().unwrap_or_default();
+        let values = offset_str.split(",")
+            .map(|s| s.trim().parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>();
+
+        match values {
+            Ok(values) if values.len() == 2 => Vec2::new(values[0], values[1]),
+            _ => return Err(format!("perform# needs perform_offset \"x,y\", got {}", offset)),
+        }
+    } else {
+        warn!("perform# event format error, perform# should have perform_offset");
+        Vec2::ZERO
+    };
+
+    // info!("perform# event, perform_offset: {:?}", perform_offset);
+    Ok(FrameEvent::PerformAt(input.translate + perform_offset))
+}
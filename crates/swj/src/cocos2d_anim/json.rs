@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum JsonErrorReason {
+    #[error("missing field")]
+    MissingField,
+    #[error("expected {0}")]
+    WrongType(&'static str),
+    #[error("{0}")]
+    BadValue(String),
+}
+
+/// A field of a json asset that is missing or malformed.
+#[derive(Debug, Error)]
+#[error("{}: {pointer}: {reason}", .path.display())]
+pub struct JsonError {
+    pub path: PathBuf,
+    /// [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) of the field
+    pub pointer: String,
+    pub reason: JsonErrorReason,
+}
+
+/// A json value that remembers the file and the pointer it was read from,
+/// so every accessor can fail with a [`JsonError`] instead of panicking.
+#[derive(Clone)]
+pub(crate) struct JsonNode<'a> {
+    path: &'a Path,
+    pointer: String,
+    value: &'a Value,
+}
+
+impl<'a> JsonNode<'a> {
+    pub fn root(path: &'a Path, value: &'a Value) -> JsonNode<'a> {
+        JsonNode {
+            path,
+            pointer: String::new(),
+            value,
+        }
+    }
+
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    pub fn value(&self) -> &'a Value {
+        self.value
+    }
+
+    pub fn error(&self, reason: JsonErrorReason) -> JsonError {
+        JsonError {
+            path: self.path.to_path_buf(),
+            pointer: self.pointer.clone(),
+            reason,
+        }
+    }
+
+    fn child_pointer(&self, key: &str) -> String {
+        format!("{}/{}", self.pointer, key.replace('~', "~0").replace('/', "~1"))
+    }
+
+    fn child(&self, key: &str, value: &'a Value) -> JsonNode<'a> {
+        JsonNode {
+            path: self.path,
+            pointer: self.child_pointer(key),
+            value,
+        }
+    }
+
+    pub fn opt(&self, key: &str) -> Option<JsonNode<'a>> {
+        self.value.get(key).map(|v| self.child(key, v))
+    }
+
+    pub fn get(&self, key: &str) -> Result<JsonNode<'a>, JsonError> {
+        match self.value.get(key) {
+            Some(v) => Ok(self.child(key, v)),
+            None => Err(JsonError {
+                path: self.path.to_path_buf(),
+                pointer: self.child_pointer(key),
+                reason: JsonErrorReason::MissingField,
+            }),
+        }
+    }
+
+    pub fn items(&self) -> Result<Vec<JsonNode<'a>>, JsonError> {
+        let array = self.value.as_array().ok_or_else(|| self.error(JsonErrorReason::WrongType("array")))?;
+        Ok(array.iter()
+            .enumerate()
+            .map(|(idx, v)| self.child(&idx.to_string(), v))
+            .collect())
+    }
+
+    pub fn str(&self) -> Result<&'a str, JsonError> {
+        self.value.as_str().ok_or_else(|| self.error(JsonErrorReason::WrongType("string")))
+    }
+
+    pub fn f32(&self) -> Result<f32, JsonError> {
+        self.value.as_f64()
+            .map(|v| v as f32)
+            .ok_or_else(|| self.error(JsonErrorReason::WrongType("number")))
+    }
+
+    pub fn i64(&self) -> Result<i64, JsonError> {
+        self.value.as_f64()
+            .map(|v| v as i64)
+            .ok_or_else(|| self.error(JsonErrorReason::WrongType("number")))
+    }
+
+    pub fn bool(&self) -> Result<bool, JsonError> {
+        self.value.as_bool().ok_or_else(|| self.error(JsonErrorReason::WrongType("bool")))
+    }
+
+    /// Read an optional number field, a present field of the wrong type is still an error.
+    pub fn f32_or(&self, key: &str, default: f32) -> Result<f32, JsonError> {
+        self.opt(key).map_or(Ok(default), |v| v.f32())
+    }
+}
@@ -1,55 +1,133 @@
+use std::any::Any;
 use std::cmp::max;
+use std::collections::VecDeque;
 use std::f32::consts::FRAC_PI_2;
+use std::sync::Arc;
 use std::time::Duration;
 
 use bevy::{
+    ecs::system::SystemParam,
     prelude::*,
 };
 use bevy::time::TimerMode::Repeating;
+use bevy::utils::HashMap;
 
 use anim::Cocos2dAnimAsset;
-use sprite_sheet::{PlistSpriteAssetLoader, PlistSpriteFrameAsset};
+use bone::attach_to_bone;
+use ccz::CczTextureLoader;
+use culling::{Cocos2dAnimCulling, cull_animators};
+use dragon_bones::{DragonBonesAssetLoader, DragonBonesAtlasLoader};
+use event::{FrameEventInput, FrameEventRegistry};
+use pool::{Cocos2dLayerPool, recycle_layers};
+use sprite_sheet::{PlistSpriteAssetLoader, PlistSpriteFrameAsset, SpriteFrameAsset};
+use state_machine::{Cocos2dAnimStateMachine, Cocos2dAnimStateMachineLoader, drive_state_machine};
+use texture_packer::TexturePackerSpriteAssetLoader;
 
-use crate::cocos2d_anim::anim::{Cocos2dAnimAssetLoader, FrameEvent};
-use crate::cocos2d_anim::anim::FrameEvent::PerformAt;
+use crate::cocos2d_anim::anim::{Cocos2dAnimAssetLoader, Cocos2dAnimBone, Cocos2dAnimFrame, display_offset, FrameEvent, key_frame_idx, LayerPose, multiply_color, skew_to_rotation};
 use crate::cocos2d_anim::AnimationState::Ended;
-use crate::cocos2d_anim::EventType::{Custom, End};
+use crate::cocos2d_anim::EventType::{Custom, End, Loop};
 
 pub mod sprite_sheet;
 pub mod anim;
+pub mod tween;
+pub mod json;
+pub mod event;
+pub mod state_machine;
+pub mod bone;
+pub mod culling;
+pub mod pool;
+pub mod texture_packer;
+pub mod ccz;
+pub mod dragon_bones;
 
-pub struct Cocos2dAnimPlugin;
+#[derive(Default)]
+pub struct Cocos2dAnimPlugin {
+    pub events: FrameEventRegistry,
+}
+
+impl Cocos2dAnimPlugin {
+    /// Register a frame event name with the parser of its payload, see [`FrameEventRegistry::register`].
+    pub fn with_event<T: Any + Send + Sync>(
+        mut self,
+        name: &str,
+        parser: impl Fn(&FrameEventInput) -> Result<T, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.events.register(name, parser);
+        self
+    }
+}
 
+/// Systems that play, seek or pause animators run before [`Cocos2dAnimSet::AdjustSprite`]
+/// to show the change in the same frame, after it they show one frame late.
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Cocos2dAnimSet {
+    /// starts the clips set on `Cocos2dAnimator`
     Update,
+    /// steps the players and poses their layers
     AdjustSprite,
 }
 
 impl Plugin for Cocos2dAnimPlugin {
     fn build(&self, app: &mut App) {
+        let events = Arc::new(self.events.clone());
+
         app
             .configure_sets(Update, (Cocos2dAnimSet::Update, Cocos2dAnimSet::AdjustSprite).chain())
             .init_asset::<PlistSpriteFrameAsset>()
+            .init_asset::<SpriteFrameAsset>()
             .init_asset_loader::<PlistSpriteAssetLoader>()
+            .init_asset_loader::<TexturePackerSpriteAssetLoader>()
+            .init_asset_loader::<CczTextureLoader>()
             .init_asset::<Cocos2dAnimAsset>()
-            .init_asset_loader::<Cocos2dAnimAssetLoader>()
+            .register_asset_loader(Cocos2dAnimAssetLoader {
+                events: events.clone(),
+            })
+            .register_asset_loader(DragonBonesAssetLoader {
+                events,
+            })
+            .init_asset_loader::<DragonBonesAtlasLoader>()
+            .init_asset::<Cocos2dAnimStateMachine>()
+            .init_asset_loader::<Cocos2dAnimStateMachineLoader>()
+            .init_resource::<Cocos2dAnimTimeScale>()
+            .init_resource::<Cocos2dAnimCulling>()
+            .init_resource::<Cocos2dLayerPool>()
             .add_event::<AnimEvent>()
             .add_systems(Update,
                          (
-                             (spawn_anim, anim_cfg_change).in_set(Cocos2dAnimSet::Update),
-                             (animate_sprite, ).in_set(Cocos2dAnimSet::AdjustSprite),
+                             (
+                                 drive_state_machine.before(spawn_anim).before(anim_cfg_change),
+                                 spawn_anim,
+                                 anim_cfg_change,
+                             ).in_set(Cocos2dAnimSet::Update),
+                             (cull_animators, animate_sprite, attach_to_bone).chain().in_set(Cocos2dAnimSet::AdjustSprite),
                          ),
             )
         ;
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AnimationMode {
     Once,
     Loop,
     Remove,
+    /// play forward then backward forever, `Loop` is sent when back on the first frame
+    PingPong,
+    /// play once from the last frame to the first
+    Reverse,
+    /// play n times, `Loop` is sent between the rounds
+    LoopCount(u32),
+    /// play once and hold the last frame, the animator never ends
+    ClampForever,
+}
+
+impl AnimationMode {
+    fn first_frame(&self, frame_size: usize) -> usize {
+        match self {
+            AnimationMode::Reverse => frame_size.saturating_sub(1),
+            _ => 0,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -66,6 +144,32 @@ pub struct Cocos2dAnimator {
     pub mode: AnimationMode,
     pub event_channel: Option<i32>,
     pub face_dir: AnimationFaceDir,
+    /// playback speed multiplier, can be changed while playing
+    pub speed: f32,
+    /// clips to play after the current one, see [`Cocos2dAnimator::then`]
+    pub queue: VecDeque<(String, AnimationMode)>,
+    /// blend from the outgoing clip over this time when the clip changes
+    pub crossfade: Option<Duration>,
+    /// displays shown instead of the animated ones, by bone name
+    pub displays: HashMap<String, DisplayOverride>,
+    /// root bone whose movement is taken out of the layers and written to [`RootMotionDelta`]
+    pub root_motion: Option<String>,
+    /// multiplied into the color of every layer
+    pub tint: Color,
+    /// multiplied into the alpha of every layer
+    pub opacity: f32,
+}
+
+/// What a bone shows instead of the display its keyframes pick.
+#[derive(Debug, Clone)]
+pub enum DisplayOverride {
+    /// another entry of the bone's `display_data`
+    Index(usize),
+    /// a frame of any sprite sheet, placed with the skin transform of the animated display
+    Sprite {
+        sheet: Handle<PlistSpriteFrameAsset>,
+        name: String,
+    },
 }
 
 impl Default for Cocos2dAnimator {
@@ -77,15 +181,60 @@ impl Default for Cocos2dAnimator {
             mode: AnimationMode::Once,
             event_channel: None,
             face_dir: AnimationFaceDir::Right,
+            speed: 1.0,
+            queue: VecDeque::new(),
+            crossfade: None,
+            displays: HashMap::new(),
+            root_motion: None,
+            tint: Color::WHITE,
+            opacity: 1.0,
         }
     }
 }
 
+/// Time scale of every animator, 0 pauses all animations. Independent of `Time<Virtual>`.
+#[derive(Resource, Debug)]
+pub struct Cocos2dAnimTimeScale(pub f32);
+
+impl Default for Cocos2dAnimTimeScale {
+    fn default() -> Self {
+        Cocos2dAnimTimeScale(1.0)
+    }
+}
+
 impl Cocos2dAnimator {
     pub fn switch_anim(&mut self, name: &str) {
         info!("switch_anim: {}", name);
         self.new_anim = Some(name.to_string());
     }
+
+    /// Play `name` right away and drop the queued clips.
+    pub fn play(&mut self, name: &str, mode: AnimationMode) -> &mut Self {
+        self.new_anim = Some(name.to_string());
+        self.mode = mode;
+        self.queue.clear();
+        self
+    }
+
+    /// Show `display` on `bone` until [`Cocos2dAnimator::clear_display`].
+    pub fn set_display(&mut self, bone: &str, display: DisplayOverride) {
+        self.displays.insert(bone.to_string(), display);
+    }
+
+    pub fn clear_display(&mut self, bone: &str) {
+        self.displays.remove(bone);
+    }
+
+    /// Replace every display override at once, e.g. with a team or equipment skin.
+    pub fn set_skin(&mut self, skin: impl IntoIterator<Item=(String, DisplayOverride)>) {
+        self.displays = skin.into_iter().collect();
+    }
+
+    /// Queue `name` after the last clip. A looping clip gives way at its next loop boundary.
+    pub fn then(&mut self, name: &str, mode: AnimationMode) -> &mut Self {
+        self.queue.push_back((name.to_string(), mode));
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -98,35 +247,208 @@ enum AnimationState {
 pub struct Cocos2dAnimatorPlayer {
     frame_idx: usize,
     timer: Timer,
+    /// playing from the last frame towards the first
+    backward: bool,
+    /// the clip plays `AnimationMode::Reverse`, progress counts from the last frame
+    reverse: bool,
+    /// finished rounds of `LoopCount` and `PingPong`
+    loops: u32,
+    /// the clip ended, looped or reached the frame it holds at least once
+    finished: bool,
+    crossfade: Option<Crossfade>,
+    root_motion: Option<RootMotion>,
+    /// world pose of every bone of the last update, in `Cocos2dAnimAsset::bones` order
+    poses: Vec<LayerPose>,
+    frame_size: usize,
+    paused: bool,
+    /// moved by a seek since the last update, wakes up an ended animator
+    seeked: bool,
+    /// out of the camera view, see [`Cocos2dAnimCulling`]
+    culled: bool,
+    /// update layers every this many frames
+    lod_stride: u32,
     pub anim_name: String,
 }
 
+impl Cocos2dAnimatorPlayer {
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The clip played through at least once: it ended, started its next round
+    /// or holds its last frame. Looping clips never end, so wait for this instead.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Current frame, `None` before the first frame of a new clip.
+    pub fn frame(&self) -> Option<usize> {
+        if self.frame_idx == usize::MAX {
+            None
+        } else {
+            Some(self.frame_idx)
+        }
+    }
+
+    /// Jump to `frame` of the playing clip without sending frame events. `asset` is the
+    /// animator's `anim_handle`. The layers show the new frame after the next
+    /// [`Cocos2dAnimSet::AdjustSprite`], also when paused or ended, seek before that set
+    /// to show it in the same frame.
+    pub fn seek_frame(&mut self, frame: usize, asset: &Cocos2dAnimAsset) {
+        // the player's own frame size lags a clip switch until that update
+        let Some(animation) = asset.animation.get(&self.anim_name) else {
+            warn!("anim {} not found in {}, seek ignored.", self.anim_name, asset.name);
+            return;
+        };
+        if self.frame_size != animation.frame_size {
+            self.frame_size = animation.frame_size;
+        }
+
+        self.frame_idx = frame.min(self.frame_size.saturating_sub(1));
+        // ping-pong starts its next round forward again
+        self.backward = self.reverse;
+        self.timer.reset();
+        self.root_motion = None;
+        self.finished = false;
+        self.seeked = true;
+    }
+
+    /// Jump to `t` in `[0, 1]` of the clip, between frames included, see [`Self::seek_frame`].
+    /// `t` counts like [`Self::progress`], from the last frame for `Reverse`.
+    pub fn seek_normalized(&mut self, t: f32, asset: &Cocos2dAnimAsset) {
+        let Some(animation) = asset.animation.get(&self.anim_name) else {
+            warn!("anim {} not found in {}, seek ignored.", self.anim_name, asset.name);
+            return;
+        };
+        let last = animation.frame_size.saturating_sub(1) as f32;
+        let t = t.clamp(0.0, 1.0);
+
+        // the timer moves a backward player towards the previous frame
+        let (frame_pos, frame) = if self.reverse {
+            let frame_pos = (1.0 - t) * last;
+            (frame_pos, frame_pos.ceil())
+        } else {
+            let frame_pos = t * last;
+            (frame_pos, frame_pos.floor())
+        };
+        self.seek_frame(frame as usize, asset);
+
+        let fraction = self.timer.duration().mul_f32((frame_pos - frame).abs());
+        self.timer.set_elapsed(fraction);
+    }
+
+    /// How far the clip has played, in `[0, 1]`. `Reverse` clips count from their last
+    /// frame, ping-pong counts down again on its way back.
+    pub fn progress(&self) -> f32 {
+        let last = self.frame_size.saturating_sub(1);
+        if self.frame_idx == usize::MAX || last == 0 {
+            return 0.0;
+        }
+
+        let frame_pos = if self.backward {
+            self.frame_idx as f32 - self.timer.fraction()
+        } else {
+            self.frame_idx as f32 + self.timer.fraction()
+        };
+        let progress = (frame_pos / last as f32).clamp(0.0, 1.0);
+
+        if self.reverse {
+            1.0 - progress
+        } else {
+            progress
+        }
+    }
+}
+
+/// Movement of the root bone in the last animation update, mirrored with the face dir.
+#[derive(Component, Debug, Default)]
+pub struct RootMotionDelta(pub Vec2);
+
+/// Root bone positions of the playing clip, relative to its first frame.
+#[derive(Debug)]
+struct RootMotion {
+    origin: Vec2,
+    end_offset: Vec2,
+    offset: Vec2,
+    frame_pos: f32,
+}
+
+/// The outgoing clip, frozen where it was left.
+#[derive(Debug)]
+struct Crossfade {
+    anim_name: String,
+    frame_pos: f32,
+    timer: Timer,
+}
+
 #[derive(Component)]
 pub struct CocoAnim2dAnimatorLayer {
     name: String,
     idx: usize,
+    bone: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum EventType {
     Custom(FrameEvent),
+    /// a looping mode starts the next round
+    Loop,
     End,
 }
 
-#[derive(Event, Debug)]
+#[derive(Event, Debug, Clone)]
 pub struct AnimEvent {
     pub entity: Entity,
     pub channel: i32,
     pub evt_type: EventType,
 }
 
+/// An animation event triggered on the animator's entity, observe it with
+/// `app.observe` or `commands.entity(e).observe` to react to one entity's events.
+/// Unlike [`AnimEvent`] it is delivered whether or not `event_channel` is set.
+#[derive(Event, Debug, Clone)]
+pub struct OnAnimEvent {
+    pub evt_type: EventType,
+}
+
 fn anim_cfg_change(
     mut commands: Commands,
+    animations: Res<Assets<Cocos2dAnimAsset>>,
     mut query: Query<(Entity, &mut Cocos2dAnimator, &mut Cocos2dAnimatorPlayer), Changed<Cocos2dAnimator>>,
 ) {
     for (entity, mut cfg, mut player) in &mut query.iter_mut() {
         if let Some(anim_name) = &cfg.new_anim {
+            // clip names come from data files too, a typo keeps the current clip
+            let known = animations.get(&cfg.anim_handle)
+                .is_some_and(|asset| asset.animation.contains_key(anim_name));
+            if !known {
+                warn!("In {:?} animation, anim {} not found, keep playing {}.", cfg.anim_handle, anim_name, player.anim_name);
+                cfg.new_anim = None;
+                continue;
+            }
+
+            player.root_motion = None;
+            player.crossfade = match cfg.crossfade {
+                Some(duration) if player.frame_idx != usize::MAX => Some(Crossfade {
+                    anim_name: player.anim_name.clone(),
+                    frame_pos: player.frame_idx as f32,
+                    timer: Timer::new(duration, TimerMode::Once),
+                }),
+                _ => None,
+            };
             player.frame_idx = usize::MAX;
+            player.backward = false;
+            player.reverse = cfg.mode == AnimationMode::Reverse;
+            player.loops = 0;
+            player.finished = false;
             player.anim_name = anim_name.clone();
             cfg.new_anim = None;
             commands.entity(entity).remove::<AnimEnded>();
@@ -144,14 +466,21 @@ struct AnimRmBundle {
 fn spawn_anim(
     mut commands: Commands,
     animations: Res<Assets<Cocos2dAnimAsset>>,
-    query: Query<(Entity, &Cocos2dAnimator), Added<Cocos2dAnimator>>,
+    mut pool: ResMut<Cocos2dLayerPool>,
+    layers: Query<(), With<CocoAnim2dAnimatorLayer>>,
+    mut query: Query<(Entity, &mut Cocos2dAnimator), Added<Cocos2dAnimator>>,
 ) {
-    for (entity, cfg) in &mut query.iter() {
-        spawn_anim_internal(&mut commands, animations.as_ref(), entity, cfg);
+    for (entity, mut cfg) in &mut query {
+        // the player plays the clip now, a left over switch would restart it
+        if spawn_anim_internal(&mut commands, animations.as_ref(), &mut pool, &layers, entity, &cfg) {
+            cfg.new_anim = None;
+        }
     }
 }
 
-fn spawn_anim_internal(commands: &mut Commands, animations: &Assets<Cocos2dAnimAsset>, entity: Entity, cfg: &Cocos2dAnimator) -> bool {
+fn spawn_anim_internal(commands: &mut Commands, animations: &Assets<Cocos2dAnimAsset>,
+                       pool: &mut Cocos2dLayerPool, layers: &Query<(), With<CocoAnim2dAnimatorLayer>>,
+                       entity: Entity, cfg: &Cocos2dAnimator) -> bool {
     let anim_asset = animations.get(cfg.anim_handle.clone()).unwrap();
     let anim_name = if let Some(name) = &cfg.new_anim {
         name.clone()
@@ -174,28 +503,59 @@ fn spawn_anim_internal(commands: &mut Commands, animations: &Assets<Cocos2dAnimA
 
     commands.entity(entity).try_insert((
         Cocos2dAnimatorPlayer {
-            frame_idx: 0,
+            // not started, the first update enters the first frame and sends its events
+            frame_idx: usize::MAX,
             timer: Timer::from_seconds(interval, Repeating),
+            backward: false,
+            reverse: cfg.mode == AnimationMode::Reverse,
+            loops: 0,
+            finished: false,
+            crossfade: None,
+            root_motion: None,
+            poses: Vec::new(),
+            frame_size: animation.frame_size,
+            paused: false,
+            seeked: false,
+            culled: false,
+            lod_stride: 1,
             anim_name,
         },
-    ))
-        .with_children(|parent| {
-            for (name, frames) in &animation.layers {
-                parent.spawn((
-                    CocoAnim2dAnimatorLayer {
-                        name: name.clone(),
-                        idx: 0,
-                    },
-                    SpriteBundle {
-                        ..default()
-                    },
-                    TextureAtlas {
-                        ..default()
-                    },
-                ));
-            }
-        });
+    ));
 
+    for name in animation.layers.keys() {
+        if anim_asset.bone_idx(name).is_none() {
+            warn!("layer {} has no bone in animation {:?}.", name, cfg.anim_handle);
+        }
+    }
+
+    // spawn in bone order so the hierarchy doesn't depend on hash order
+    let mut layer_entities = Vec::with_capacity(animation.layers.len());
+    for (bone, bone_data) in anim_asset.bones.iter().enumerate() {
+        if !animation.layers.contains_key(&bone_data.name) {
+            continue;
+        }
+
+        let layer = CocoAnim2dAnimatorLayer {
+            name: bone_data.name.clone(),
+            idx: 0,
+            bone,
+        };
+        // pooled layers stay hidden until the first update sets their display
+        let layer_entity = match pool.take(layers) {
+            Some(pooled) => commands.entity(pooled).try_insert(layer).id(),
+            None => commands.spawn((
+                layer,
+                SpriteBundle {
+                    ..default()
+                },
+                TextureAtlas {
+                    ..default()
+                },
+            )).id(),
+        };
+        layer_entities.push(layer_entity);
+    }
+    commands.entity(entity).push_children(&layer_entities);
 
     true
 }
@@ -203,70 +563,410 @@ fn spawn_anim_internal(commands: &mut Commands, animations: &Assets<Cocos2dAnimA
 #[derive(Component)]
 struct AnimEnded;
 
+/// Local z of all layers stays in `[0, LAYER_Z_RANGE)`, small enough not to
+/// overlap the y sorting of `OrderElement` between units.
+pub const LAYER_Z_RANGE: f32 = 0.1;
+
+enum FrameStep {
+    Next,
+    /// moved to the first frame of the next round
+    Looped,
+    Hold,
+    Ended,
+}
+
+/// Move the player one frame along `mode`.
+fn step_frame(player: &mut Cocos2dAnimatorPlayer, mode: AnimationMode, frame_size: usize) -> FrameStep {
+    let last = frame_size.saturating_sub(1);
+
+    if player.frame_idx == usize::MAX {
+        player.frame_idx = mode.first_frame(frame_size);
+        player.reverse = mode == AnimationMode::Reverse;
+        player.backward = player.reverse;
+        return FrameStep::Next;
+    }
+
+    if player.backward {
+        if player.frame_idx > 0 {
+            player.frame_idx -= 1;
+            return FrameStep::Next;
+        }
+
+        return match mode {
+            AnimationMode::PingPong => {
+                player.loops += 1;
+                player.backward = false;
+                player.frame_idx = 1.min(last);
+                FrameStep::Looped
+            }
+            _ => FrameStep::Ended,
+        };
+    }
+
+    if player.frame_idx < last {
+        player.frame_idx += 1;
+        return FrameStep::Next;
+    }
+
+    match mode {
+        AnimationMode::Loop => {
+            player.frame_idx = 0;
+            FrameStep::Looped
+        }
+        AnimationMode::LoopCount(n) if player.loops + 1 < n => {
+            player.loops += 1;
+            player.frame_idx = 0;
+            FrameStep::Looped
+        }
+        AnimationMode::PingPong => {
+            player.backward = true;
+            player.frame_idx = last.saturating_sub(1);
+            FrameStep::Next
+        }
+        AnimationMode::ClampForever => FrameStep::Hold,
+        _ => FrameStep::Ended,
+    }
+}
+
+/// Whether the player just reached the frame that finishes the animation.
+fn reached_end(player: &Cocos2dAnimatorPlayer, mode: AnimationMode, frame_size: usize) -> bool {
+    let last = frame_size.saturating_sub(1);
+
+    match mode {
+        AnimationMode::Reverse => player.frame_idx == 0,
+        AnimationMode::LoopCount(n) => player.frame_idx == last && player.loops + 1 >= n,
+        AnimationMode::PingPong => false,
+        _ => player.frame_idx == last,
+    }
+}
+
+/// The sprite a layer shows and its skin transform in bone space.
+struct LayerDisplay {
+    offset: Vec3,
+    scale: Vec2,
+    skew: Vec2,
+    rotated: bool,
+    atlas: Handle<TextureAtlasLayout>,
+    texture: Handle<Image>,
+    sprite_idx: usize,
+}
+
+impl LayerDisplay {
+    fn from_frame(frame: &Cocos2dAnimFrame) -> LayerDisplay {
+        LayerDisplay {
+            offset: frame.display_offset,
+            scale: frame.display_scale,
+            skew: frame.display_skew,
+            rotated: frame.rotated,
+            atlas: frame.sprite_atlas.clone(),
+            texture: frame.texture.clone(),
+            sprite_idx: frame.sprite_idx,
+        }
+    }
+
+    /// `None` while the sheet is not loaded or has no such sprite frame.
+    fn from_override(frame: &Cocos2dAnimFrame,
+                     bone: &Cocos2dAnimBone,
+                     display_override: &DisplayOverride,
+                     sheets: &Assets<PlistSpriteFrameAsset>) -> Option<LayerDisplay> {
+        let (skin, sheet, name) = match display_override {
+            DisplayOverride::Index(idx) => {
+                let display = bone.displays.get(*idx)?;
+                (display, display.sheet.as_ref()?, &display.name)
+            }
+            DisplayOverride::Sprite { sheet, name } => {
+                (bone.displays.get(frame.di?)?, sheet, name)
+            }
+        };
+
+        let sheet = sheets.get(sheet)?;
+        let sprite_idx = sheet.frame_index(name)?;
+        let sprite_frame = &sheet.frames[sprite_idx];
+
+        Some(LayerDisplay {
+            offset: display_offset(skin, sprite_frame),
+            scale: skin.scale,
+            skew: skin.skew,
+            rotated: sprite_frame.rotated,
+            atlas: sheet.atlas.clone(),
+            texture: sheet.texture.clone(),
+            sprite_idx,
+        })
+    }
+}
+
+fn layer_transform(display: &LayerDisplay, pose: &LayerPose, face_dir: &AnimationFaceDir) -> Transform {
+    let bone_rotation = pose.rotation();
+    let offset = Quat::from_rotation_z(bone_rotation) * (display.offset * pose.scale.extend(1.0));
+
+    let mut translation = pose.translate + offset;
+    let mut rotation = bone_rotation + skew_to_rotation(display.skew);
+    let mut atlas_rotation = FRAC_PI_2;
+    let mut scale = display.scale * pose.scale;
+
+    // mirror around the y axis, the sprite itself is flipped by `Sprite::flip_x`
+    if *face_dir == AnimationFaceDir::Left {
+        translation.x = -translation.x;
+        rotation = -rotation;
+        atlas_rotation = -atlas_rotation;
+    }
+
+    let rotation = if display.rotated {
+        // scale is applied before the atlas rotation, so swap it into the rotated quad's axes
+        scale = scale.yx();
+        Quat::from_rotation_z(rotation) * Quat::from_rotation_z(atlas_rotation)
+    } else {
+        Quat::from_rotation_z(rotation)
+    };
+
+    Transform {
+        translation,
+        rotation,
+        scale: scale.extend(1.0),
+    }
+}
+
+/// The resources [`animate_sprite`] reads and the buffers it reuses between runs.
+#[derive(SystemParam)]
+struct AnimateSpriteParams<'w, 's> {
+    time: Res<'w, Time>,
+    time_scale: Res<'w, Cocos2dAnimTimeScale>,
+    animations: Res<'w, Assets<Cocos2dAnimAsset>>,
+    sheets: Res<'w, Assets<PlistSpriteFrameAsset>>,
+    poses: Local<'s, Vec<LayerPose>>,
+    fade_poses: Local<'s, Vec<LayerPose>>,
+    root_poses: Local<'s, Vec<LayerPose>>,
+    draw_order: Local<'s, (Vec<usize>, Vec<usize>)>,
+    frame_count: Local<'s, u32>,
+}
+
 fn animate_sprite(
     mut commands: Commands,
-    time: Res<Time>,
-    animations: Res<Assets<Cocos2dAnimAsset>>,
-    mut query: Query<(Entity, &mut Cocos2dAnimator, &mut Cocos2dAnimatorPlayer, &Children), Without<AnimEnded>>,
-    mut child_query: Query<(&mut Sprite, &mut Handle<Image>, &mut CocoAnim2dAnimatorLayer, &mut TextureAtlas, &mut Transform)>,
+    params: AnimateSpriteParams,
+    mut query: Query<(Entity, &mut Cocos2dAnimator, &mut Cocos2dAnimatorPlayer, &Children, Option<&mut RootMotionDelta>, Has<AnimEnded>)>,
+    mut child_query: Query<(&mut Sprite, &mut Handle<Image>, &mut CocoAnim2dAnimatorLayer, &mut TextureAtlas, &mut Transform, &mut Visibility)>,
     mut events: EventWriter<AnimEvent>,
 ) {
-    for (entity, cfg, mut animator, children) in &mut query {
+    let AnimateSpriteParams {
+        time,
+        time_scale,
+        animations,
+        sheets,
+        mut poses,
+        mut fade_poses,
+        mut root_poses,
+        mut draw_order,
+        mut frame_count,
+    } = params;
+    *frame_count = frame_count.wrapping_add(1);
+
+    for (entity, mut cfg, mut animator, children, mut root_delta, ended) in &mut query {
+        if ended {
+            if !animator.seeked {
+                continue;
+            }
+            commands.entity(entity).remove::<AnimEnded>();
+        }
+
+        if let Some(root_delta) = root_delta.as_mut() {
+            if root_delta.0 != Vec2::ZERO {
+                root_delta.0 = Vec2::ZERO;
+            }
+        }
+
         // info!("animate_sprite: {:?}, interval: {}", animator, animator.timer.duration().as_secs_f32());
-        animator.timer.tick(time.delta());
-        if !animator.timer.just_finished() {
+        let speed = if animator.paused {
+            0.0
+        } else {
+            (cfg.speed * time_scale.0).max(0.0)
+        };
+        let delta = time.delta().mul_f32(speed);
+        animator.timer.tick(delta);
+        // a new clip enters its first frame right away instead of after one interval
+        let steps = animator.timer.times_finished_this_tick() + u32::from(animator.frame_idx == usize::MAX);
+
+        let Some(anim_asset) = animations.get(&cfg.anim_handle) else {
+            continue;
+        };
+        let Some(animation) = anim_asset.animation.get(&animator.anim_name) else {
             continue;
+        };
+
+        if animator.frame_size != animation.frame_size {
+            animator.frame_size = animation.frame_size;
+        }
+        if animator.frame_idx != usize::MAX && animator.frame_idx >= animation.frame_size {
+            animator.frame_idx = animation.frame_size.saturating_sub(1);
         }
+        let seeked = animator.seeked;
+        if seeked {
+            animator.seeked = false;
+        }
+
+        // a long tick crosses several frames, play every one of them so no event gets lost
+        let mut stopped = false;
+        for _ in 0..steps {
+            // info!("animate_sprite: {}", animator.frame_idx);
+
+            let step = step_frame(&mut animator, cfg.mode, animation.frame_size);
+            let clip_done = matches!(step, FrameStep::Looped | FrameStep::Hold | FrameStep::Ended);
+            if clip_done && !animator.finished {
+                animator.finished = true;
+            }
+            if clip_done && !cfg.queue.is_empty() {
+                let (name, mode) = cfg.queue.pop_front().unwrap();
+                cfg.new_anim = Some(name);
+                cfg.mode = mode;
+                stopped = true;
+                break;
+            }
+
+            let looped = match step {
+                FrameStep::Next => false,
+                FrameStep::Looped => true,
+                FrameStep::Hold => break,
+                FrameStep::Ended => {
+                    if cfg.mode == AnimationMode::Remove {
+                        commands.add(move |world: &mut World| recycle_layers(world, entity));
+                        commands.entity(entity).remove::<AnimRmBundle>();
+                    } else {
+                        commands.entity(entity).try_insert(AnimEnded);
+                    }
+                    stopped = true;
+                    break;
+                }
+            };
+
+            let channel = cfg.event_channel;
+            let mut send = |evt_type: EventType| {
+                if let Some(channel) = channel {
+                    events.send(AnimEvent {
+                        entity,
+                        channel,
+                        evt_type: evt_type.clone(),
+                    });
+                }
+                commands.trigger_targets(OnAnimEvent { evt_type }, entity);
+            };
+
+            if looped {
+                send(Loop);
+            }
 
-        // info!("animate_sprite: {}", animator.frame_idx);
+            for evt in anim_asset.events_at(animation, animator.frame_idx) {
+                send(Custom(evt.clone()));
+            }
 
-        let animation = animations.get(cfg.anim_handle.clone()).unwrap();
-        let animation = &animation.animation[&animator.anim_name];
+            if reached_end(&animator, cfg.mode, animation.frame_size) {
+                send(End);
+            }
+        }
 
+        if stopped {
+            continue;
+        }
 
         if animator.frame_idx == usize::MAX {
-            animator.frame_idx = 0;
-        } else if animator.frame_idx + 1 >= animation.frame_size {
-            match cfg.mode {
-                AnimationMode::Once => {
-                    commands.entity(entity).try_insert(AnimEnded);
-                    continue;
+            continue;
+        }
+
+        if let Some(fade) = &mut animator.crossfade {
+            fade.timer.tick(delta);
+            if fade.timer.finished() {
+                animator.crossfade = None;
+            }
+        }
+
+        // culled and low detail animators still step frames and send events above,
+        // only root motion needs their poses
+        let update_layers = seeked
+            || (!animator.culled && (*frame_count).wrapping_add(entity.index()) % animator.lod_stride.max(1) == 0);
+        if !update_layers && cfg.root_motion.is_none() {
+            continue;
+        }
+
+        let last_frame = animation.frame_size.saturating_sub(1);
+        let frame_pos = if animator.backward {
+            animator.frame_idx as f32 - animator.timer.fraction()
+        } else {
+            animator.frame_idx as f32 + animator.timer.fraction()
+        };
+        let frame_pos = frame_pos.clamp(0.0, last_frame as f32);
+        anim_asset.sample_bones(animation, frame_pos as usize, frame_pos, &mut poses);
+
+        if let Some(fade) = &animator.crossfade {
+            if let Some(from) = anim_asset.animation.get(&fade.anim_name) {
+                anim_asset.sample_bones(from, fade.frame_pos as usize, fade.frame_pos, &mut fade_poses);
+                let t = fade.timer.fraction();
+                for (pose, from_pose) in poses.iter_mut().zip(fade_poses.iter()) {
+                    *pose = from_pose.lerp(pose, t);
                 }
-                AnimationMode::Loop => {
-                    animator.frame_idx = 0;
+            }
+        }
+
+        let root_bone = cfg.root_motion.as_ref().and_then(|name| anim_asset.bone_idx(name));
+        if let Some(root_bone) = root_bone {
+            let mut root_at = |frame: usize| {
+                anim_asset.sample_bones(animation, frame, frame as f32, &mut root_poses);
+                root_poses[root_bone].translate.truncate()
+            };
+
+            let root = poses[root_bone].translate.truncate();
+            let backward = animator.backward;
+            let motion = animator.root_motion.get_or_insert_with(|| {
+                let origin = root_at(0);
+                RootMotion {
+                    origin,
+                    end_offset: root_at(last_frame) - origin,
+                    offset: root - origin,
+                    frame_pos,
                 }
-                AnimationMode::Remove => {
-                    for child in children.iter() {
-                        if child_query.get_mut(*child).is_ok() {
-                            commands.entity(*child).despawn_recursive();
-                        }
-                    }
-                    commands.entity(entity).remove::<AnimRmBundle>();
+            });
+
+            let offset = root - motion.origin;
+            let delta = if frame_pos < motion.frame_pos && !backward {
+                // wrapped to the next round, finish the old one first
+                motion.end_offset - motion.offset + offset
+            } else {
+                offset - motion.offset
+            };
+            motion.offset = offset;
+            motion.frame_pos = frame_pos;
+
+            for pose in poses.iter_mut() {
+                pose.translate -= offset.extend(0.0);
+            }
+
+            let delta = match cfg.face_dir {
+                AnimationFaceDir::Left => Vec2::new(-delta.x, delta.y),
+                AnimationFaceDir::Right => delta,
+            };
+            match root_delta {
+                Some(mut root_delta) => root_delta.0 = delta,
+                None => {
+                    commands.entity(entity).try_insert(RootMotionDelta(delta));
                 }
             }
-        } else {
-            animator.frame_idx += 1;
         }
 
-        if animator.frame_idx + 1 >= animation.frame_size {
-            if let Some(channel) = &cfg.event_channel {
-                let evt = AnimEvent {
-                    entity: entity.clone(),
-                    channel: *channel,
-                    evt_type: End,
-                };
+        animator.poses.clone_from(&poses);
 
-                events.send(evt);
-            }
+        if !update_layers {
+            continue;
         }
 
+        let tint = multiply_color(cfg.tint, Color::srgba(1.0, 1.0, 1.0, cfg.opacity));
+        let (order, ranks) = &mut *draw_order;
+        anim_asset.draw_order(animation, animator.frame_idx, order, ranks);
+        let z_step = LAYER_Z_RANGE / anim_asset.bones.len().max(1) as f32;
 
         for child in children.iter() {
             let (mut sprite,
                 mut texture,
                 mut layer,
                 mut atlas,
-                mut transform) = match child_query.get_mut(*child) {
+                mut transform,
+                mut visibility) = match child_query.get_mut(*child) {
                 Ok(v) => v,
                 Err(_) => {
                     warn!("layer {:?} not found in animation {:?}.", child, cfg.anim_handle);
@@ -282,82 +982,210 @@ fn animate_sprite(
                 continue;
             };
 
-
-            if animator.frame_idx == 0 {
-                layer.idx = 0;
+            if frames.is_empty() {
+                continue;
             }
 
-            if layer.idx + 1 < frames.len() {
-                let next_frame = &frames[layer.idx + 1];
-                if next_frame.fi <= animator.frame_idx {
-                    layer.idx += 1;
-                }
-            }
+            layer.idx = key_frame_idx(frames, animator.frame_idx);
 
             let frame = &frames[layer.idx];
-            atlas.index = frame.sprite_idx;
-            sprite.color = if let Some(color) = frame.color {
-                color
+            let pose = &poses[layer.bone];
+            *visibility = if frame.visible {
+                Visibility::Inherited
             } else {
-                Color::WHITE
+                Visibility::Hidden
+            };
+            let display = match cfg.displays.get(&layer.name) {
+                Some(display_override) if frame.visible => {
+                    let bone = &anim_asset.bones[layer.bone];
+                    LayerDisplay::from_override(frame, bone, display_override, &sheets)
+                        .unwrap_or_else(|| LayerDisplay::from_frame(frame))
+                }
+                _ => LayerDisplay::from_frame(frame),
             };
-            *texture = frame.texture.clone();
-            atlas.layout = frame.sprite_atlas.clone();
+
+            atlas.index = display.sprite_idx;
+            sprite.color = multiply_color(pose.color, tint);
+            *texture = display.texture.clone();
+            atlas.layout = display.atlas.clone();
 
             sprite.flip_x = match cfg.face_dir {
                 AnimationFaceDir::Left => true,
                 AnimationFaceDir::Right => false,
             };
 
-
-            if sprite.flip_x {
-                if frame.rotated {
-                    transform.rotation = Quat::from_rotation_z(-FRAC_PI_2);
-                } else {
-                    transform.rotation = Quat::IDENTITY;
-                }
-                transform.translation = frame.translate;
-                transform.translation.x *= -1.0;
-                transform.scale = frame.scale.extend(0.0);
-            } else {
-                if frame.rotated {
-                    transform.rotation = Quat::from_rotation_z(FRAC_PI_2) * Quat::IDENTITY;
-                } else {
-                    transform.rotation = Quat::IDENTITY;
-                }
-
-                transform.translation = frame.translate;
-                transform.scale = frame.scale.extend(1.0);
-            }
+            *transform = layer_transform(&display, pose, &cfg.face_dir);
+            transform.translation.z = ranks[layer.bone] as f32 * z_step;
 
             // info!("layer {} set frame: {:?}, transform: {:?}",layer.name, frame,*transform);
             // info!("layer {} set frame: {:?}, transform: {:?}, anim: {}, face_dir: {:?}, transform: {:?}",layer.name, frame,*transform,animator.anim_name,cfg.face_dir,transform);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn new_player(frame_size: usize) -> Cocos2dAnimatorPlayer {
+        Cocos2dAnimatorPlayer {
+            frame_idx: usize::MAX,
+            timer: Timer::from_seconds(0.1, Repeating),
+            backward: false,
+            reverse: false,
+            loops: 0,
+            finished: false,
+            crossfade: None,
+            root_motion: None,
+            poses: Vec::new(),
+            frame_size,
+            paused: false,
+            seeked: false,
+            culled: false,
+            lod_stride: 1,
+            anim_name: "test".to_string(),
+        }
+    }
 
+    /// `steps` frames of a new clip, each with the events `animate_sprite` sends for it.
+    /// Stops at the end of the clip, held frames are marked with `hold`.
+    fn play(mode: AnimationMode, frame_size: usize, steps: usize) -> Vec<String> {
+        let mut player = new_player(frame_size);
+        let mut frames = Vec::new();
 
-            if let Some(evt) = &frame.evt {
-                if let Some(channel) = &cfg.event_channel {
-                    let evt = match evt {
-                        PerformAt(offset) => {
-                            AnimEvent {
-                                entity: entity.clone(),
-                                channel: *channel,
-                                evt_type: Custom(PerformAt(offset.clone())),
-                            }
-                        }
-                        _ => {
-                            AnimEvent {
-                                entity: entity.clone(),
-                                channel: *channel,
-                                evt_type: Custom(evt.clone()),
-                            }
-                        }
-                    };
-
-                    events.send(evt);
+        for _ in 0..steps {
+            let looped = match step_frame(&mut player, mode, frame_size) {
+                FrameStep::Next => false,
+                FrameStep::Looped => true,
+                FrameStep::Hold => {
+                    frames.push(format!("{} hold", player.frame_idx));
+                    continue;
                 }
+                FrameStep::Ended => break,
+            };
+
+            let mut frame = player.frame_idx.to_string();
+            if looped {
+                frame.push_str(" loop");
+            }
+            if reached_end(&player, mode, frame_size) {
+                frame.push_str(" end");
             }
+            frames.push(frame);
         }
+
+        frames
+    }
+
+    #[test]
+    fn once_ends_on_the_last_frame() {
+        assert_eq!(play(AnimationMode::Once, 3, 10), ["0", "1", "2 end"]);
+        assert_eq!(play(AnimationMode::Remove, 3, 10), ["0", "1", "2 end"]);
     }
-}
 
+    #[test]
+    fn loop_ends_every_round() {
+        assert_eq!(play(AnimationMode::Loop, 3, 7), ["0", "1", "2 end", "0 loop", "1", "2 end", "0 loop"]);
+    }
+
+    #[test]
+    fn reverse_ends_on_the_first_frame() {
+        assert_eq!(play(AnimationMode::Reverse, 3, 10), ["2", "1", "0 end"]);
+    }
+
+    #[test]
+    fn ping_pong_loops_on_the_way_back_and_never_ends() {
+        assert_eq!(play(AnimationMode::PingPong, 3, 9), ["0", "1", "2", "1", "0", "1 loop", "2", "1", "0"]);
+    }
+
+    #[test]
+    fn loop_count_ends_after_its_rounds() {
+        assert_eq!(play(AnimationMode::LoopCount(2), 3, 10), ["0", "1", "2", "0 loop", "1", "2 end"]);
+        // no rounds left to loop, played once like `Once`
+        assert_eq!(play(AnimationMode::LoopCount(0), 3, 10), ["0", "1", "2 end"]);
+        assert_eq!(play(AnimationMode::LoopCount(1), 3, 10), ["0", "1", "2 end"]);
+    }
 
+    #[test]
+    fn clamp_forever_holds_the_last_frame() {
+        assert_eq!(play(AnimationMode::ClampForever, 3, 5), ["0", "1", "2 end", "2 hold", "2 hold"]);
+    }
+
+    #[test]
+    fn single_frame_clips() {
+        assert_eq!(play(AnimationMode::Once, 1, 3), ["0 end"]);
+        assert_eq!(play(AnimationMode::Reverse, 1, 3), ["0 end"]);
+        assert_eq!(play(AnimationMode::Loop, 1, 3), ["0 end", "0 loop end", "0 loop end"]);
+        assert_eq!(play(AnimationMode::LoopCount(2), 1, 3), ["0", "0 loop end"]);
+        assert_eq!(play(AnimationMode::PingPong, 1, 4), ["0", "0", "0 loop", "0"]);
+        assert_eq!(play(AnimationMode::ClampForever, 1, 3), ["0 end", "0 hold", "0 hold"]);
+    }
+
+    #[derive(Resource, Default)]
+    struct SeekTo(Option<usize>);
+
+    fn seek(
+        mut seek_to: ResMut<SeekTo>,
+        animations: Res<Assets<Cocos2dAnimAsset>>,
+        mut query: Query<(&Cocos2dAnimator, &mut Cocos2dAnimatorPlayer)>,
+    ) {
+        let Some(frame) = seek_to.0.take() else {
+            return;
+        };
+        for (animator, mut player) in &mut query {
+            player.seek_frame(frame, animations.get(&animator.anim_handle).unwrap());
+        }
+    }
+
+    /// A seek before `AdjustSprite` shows on the layers in the same update, paused too.
+    #[test]
+    fn seek_shows_in_the_same_update() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin {
+            file_path: "testdata".to_string(),
+            ..default()
+        }, Cocos2dAnimPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_resource::<SeekTo>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(10)))
+            .add_systems(Update, seek.before(Cocos2dAnimSet::AdjustSprite));
+
+        let anim: Handle<Cocos2dAnimAsset> = app.world().resource::<AssetServer>().load("dragon_bones/hero.ske.json");
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<Assets<Cocos2dAnimAsset>>().contains(&anim) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(app.world().resource::<Assets<Cocos2dAnimAsset>>().contains(&anim), "hero.ske.json didn't load in time");
+
+        let hero = app.world_mut().spawn((
+            SpatialBundle::default(),
+            Cocos2dAnimator {
+                anim_handle: anim,
+                new_anim: Some("attack".to_string()),
+                ..default()
+            },
+        )).id();
+        app.update();
+        app.world_mut().get_mut::<Cocos2dAnimatorPlayer>(hero).unwrap().pause();
+        app.update();
+        assert_eq!(app.world().get::<Cocos2dAnimatorPlayer>(hero).unwrap().frame(), Some(0));
+
+        let weapon = |app: &mut App| {
+            let mut layers = app.world_mut().query::<(&CocoAnim2dAnimatorLayer, &Visibility)>();
+            layers.iter(app.world()).find(|(layer, _)| layer.name == "weapon").map(|(_, visibility)| *visibility)
+        };
+        assert_eq!(weapon(&mut app), Some(Visibility::Inherited));
+
+        // the weapon is hidden from frame 6 on
+        app.world_mut().resource_mut::<SeekTo>().0 = Some(7);
+        app.update();
+        assert_eq!(app.world().get::<Cocos2dAnimatorPlayer>(hero).unwrap().frame(), Some(7));
+        assert_eq!(weapon(&mut app), Some(Visibility::Hidden));
+    }
+}
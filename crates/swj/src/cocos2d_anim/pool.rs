@@ -0,0 +1,101 @@
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+
+use crate::cocos2d_anim::CocoAnim2dAnimatorLayer;
+
+/// Layer entities of removed animators. New animators take their layers from here
+/// before spawning new ones, so short lived animators like projectiles don't churn entities.
+/// Pooled layers are children of a hidden root, they only change parents and never
+/// lose their `Parent`, which would move them to another archetype and back.
+#[derive(Resource, Debug)]
+pub struct Cocos2dLayerPool {
+    free: Vec<Entity>,
+    root: Option<Entity>,
+    /// layers beyond this are despawned instead of pooled
+    pub capacity: usize,
+}
+
+impl Default for Cocos2dLayerPool {
+    fn default() -> Self {
+        Cocos2dLayerPool {
+            free: Vec::new(),
+            root: None,
+            capacity: 1024,
+        }
+    }
+}
+
+impl Cocos2dLayerPool {
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    /// A pooled layer that still exists, pooled layers can be despawned by anyone.
+    pub(crate) fn take(&mut self, layers: &Query<(), With<CocoAnim2dAnimatorLayer>>) -> Option<Entity> {
+        while let Some(layer) = self.free.pop() {
+            if layers.contains(layer) {
+                return Some(layer);
+            }
+        }
+
+        None
+    }
+}
+
+/// Detach the layers of `entity` into the [`Cocos2dLayerPool`], hidden.
+pub(crate) fn recycle_layers(world: &mut World, entity: Entity) {
+    let Some(children) = world.get::<Children>(entity) else {
+        return;
+    };
+    let layers: Vec<Entity> = children.iter()
+        .copied()
+        .filter(|child| world.get::<CocoAnim2dAnimatorLayer>(*child).is_some())
+        .collect();
+
+    world.resource_scope(|world, mut pool: Mut<Cocos2dLayerPool>| {
+        let root = match pool.root.filter(|root| world.get_entity(*root).is_some()) {
+            Some(root) => root,
+            None => {
+                let root = world.spawn(SpatialBundle {
+                    visibility: Visibility::Hidden,
+                    ..default()
+                }).id();
+                pool.root = Some(root);
+                root
+            }
+        };
+
+        for layer in layers {
+            if pool.free.len() < pool.capacity {
+                let mut layer_entity = world.entity_mut(layer);
+                layer_entity.set_parent(root);
+                if let Some(mut visibility) = layer_entity.get_mut::<Visibility>() {
+                    *visibility = Visibility::Hidden;
+                }
+                pool.free.push(layer);
+            } else {
+                world.entity_mut(layer).despawn_recursive();
+            }
+        }
+    });
+}
+
+pub trait DespawnAnimatorExt {
+    /// Like `despawn_recursive`, but the animator layers go back to the [`Cocos2dLayerPool`].
+    fn despawn_animator(self);
+}
+
+impl DespawnAnimatorExt for EntityCommands<'_> {
+    fn despawn_animator(mut self) {
+        self.add(|entity: Entity, world: &mut World| {
+            recycle_layers(world, entity);
+            if let Some(entity) = world.get_entity_mut(entity) {
+                entity.despawn_recursive();
+            }
+        });
+    }
+}
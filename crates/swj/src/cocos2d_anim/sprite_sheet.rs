@@ -1,3 +1,6 @@
+use std::f32::consts::FRAC_PI_2;
+use std::path::{Path, PathBuf};
+
 use bevy::{
     asset::{AssetLoader, io::Reader, LoadContext},
     prelude::*,
@@ -5,6 +8,7 @@ use bevy::{
     utils::BoxedFuture,
 };
 use bevy::asset::AsyncReadExt;
+use bevy::utils::HashMap;
 use plist::Dictionary;
 use plist::Value;
 use thiserror::Error;
@@ -15,111 +19,478 @@ pub struct PlistSpriteAssetLoader {}
 
 #[derive(Debug, Clone)]
 pub struct SpriteFrame {
-    pub(crate) name: String,
-    pub(crate) frame: (f32, f32, f32, f32),
-    pub(crate) offset: (f32, f32),
-    pub(crate) rotated: bool,
-    pub(crate) source_color_rect: (f32, f32, f32, f32),
-    pub(crate) source_size: (f32, f32),
-}
-
-fn parse_frame_from_plist(frame_attr: &Dictionary, frame_name: &str) -> SpriteFrame {
-    let mut sf = SpriteFrame {
-        name: frame_name.to_string().replace(".png", ""),
-        frame: (0.0f32, 0.0f32, 0.0f32, 0.0f32),
-        offset: (0.0f32, 0.0f32),
-        rotated: false,
-        source_color_rect: (0.0f32, 0.0f32, 0.0f32, 0.0f32),
-        source_size: (0.0f32, 0.0f32),
-    };
+    pub name: String,
+    /// rect in the texture, with the size of the unrotated sprite
+    pub frame: (f32, f32, f32, f32),
+    /// from the center of the untrimmed image to the center of the trimmed sprite, y up
+    pub offset: (f32, f32),
+    /// stored turned 90 degrees clockwise in the texture
+    pub rotated: bool,
+    pub source_color_rect: (f32, f32, f32, f32),
+    /// size of the untrimmed image
+    pub source_size: (f32, f32),
+    /// normalized, y up like cocos anchors; only sheets that store one have it
+    pub pivot: Option<(f32, f32)>,
+}
 
-    if let Some(Value::String(s)) = frame_attr.get("frame") {
-        let s = s.replace("{", "").replace("}", "");
-        let mut frame = s.split(",");
-        sf.frame.0 = frame.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.frame.1 = frame.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.frame.2 = frame.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.frame.3 = frame.next().unwrap().parse::<i32>().unwrap() as f32;
+impl SpriteFrame {
+    /// From `anchor` (normalized, y up) of the untrimmed image to the center of the
+    /// trimmed sprite. Anchors land on whole pixels, like cocos armatures place them.
+    pub fn anchor_offset(&self, anchor: Vec2) -> Vec2 {
+        let source_size = Vec2::from(self.source_size);
+        Vec2::from(self.offset) - ((source_size * anchor).round() - source_size / 2.0)
     }
 
-    if let Some(Value::String(s)) = frame_attr.get("offset") {
-        let s = s.replace("{", "").replace("}", "");
-        let mut offset = s.split(",");
-        sf.offset.0 = offset.next().unwrap().parse::<f32>().unwrap();
-        sf.offset.1 = offset.next().unwrap().parse::<f32>().unwrap();
+    /// Places the sprite so the pivot of the untrimmed image is at the origin and the
+    /// sprite looks like it was never trimmed or rotated in the sheet.
+    pub fn local_transform(&self) -> Transform {
+        let pivot = Vec2::from(self.pivot.unwrap_or((0.5, 0.5)));
+
+        Transform {
+            translation: self.anchor_offset(pivot).extend(0.0),
+            rotation: if self.rotated {
+                Quat::from_rotation_z(FRAC_PI_2)
+            } else {
+                Quat::IDENTITY
+            },
+            ..default()
+        }
     }
+}
+
+/// Sprite of atlas entry `index` showing `frame` at `transform`.
+fn frame_sprite_bundle(frame: &SpriteFrame, index: usize, atlas: &Handle<TextureAtlasLayout>,
+                       texture: &Handle<Image>, transform: Transform) -> (SpriteBundle, TextureAtlas) {
+    (
+        SpriteBundle {
+            texture: texture.clone(),
+            transform: transform * frame.local_transform(),
+            ..default()
+        },
+        TextureAtlas {
+            layout: atlas.clone(),
+            index,
+        },
+    )
+}
 
-    if let Some(Value::Boolean(b)) = frame_attr.get("rotated") {
-        sf.rotated = *b;
+/// Parse the numbers of a cocos plist string like `{{1,2},{3,4}}`.
+fn parse_numbers(s: &str, count: usize) -> Option<Vec<f32>> {
+    let numbers = s.replace("{", "").replace("}", "")
+        .split(",")
+        .map(|n| n.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .ok()?;
+
+    if numbers.len() == count {
+        Some(numbers)
+    } else {
+        None
     }
+}
+
+/// Read a plist string field holding `count` numbers, `None` if the field is absent.
+fn plist_numbers(dict: &Dictionary, key: &str, count: usize, key_path: &str, path: &Path)
+                 -> Result<Option<Vec<f32>>, PlistSpriteAssetLoaderError> {
+    let value = match dict.get(key) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let key = format!("{}/{}", key_path, key);
+    let s = value.as_string().ok_or_else(|| PlistSpriteAssetLoaderError::WrongType {
+        path: path.to_path_buf(),
+        key: key.clone(),
+        expected: "string",
+    })?;
+
+    parse_numbers(s, count)
+        .map(Some)
+        .ok_or_else(|| PlistSpriteAssetLoaderError::BadValue {
+            path: path.to_path_buf(),
+            key,
+            value: s.to_string(),
+        })
+}
+
+/// Read a plist integer or real field, `None` if the field is absent.
+fn plist_number(dict: &Dictionary, key: &str, key_path: &str, path: &Path)
+                -> Result<Option<f32>, PlistSpriteAssetLoaderError> {
+    let value = match dict.get(key) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
 
-    if let Some(Value::String(s)) = frame_attr.get("sourceColorRect") {
-        let s = s.replace("{", "").replace("}", "");
-        let mut source_color_rect = s.split(",");
-        sf.source_color_rect.0 = source_color_rect.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.source_color_rect.1 = source_color_rect.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.source_color_rect.2 = source_color_rect.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.source_color_rect.3 = source_color_rect.next().unwrap().parse::<i32>().unwrap() as f32;
+    value.as_real()
+        .or_else(|| value.as_signed_integer().map(|n| n as f64))
+        .map(|n| Some(n as f32))
+        .ok_or_else(|| PlistSpriteAssetLoaderError::WrongType {
+            path: path.to_path_buf(),
+            key: format!("{}/{}", key_path, key),
+            expected: "number",
+        })
+}
+
+fn plist_bool(dict: &Dictionary, key: &str, key_path: &str, path: &Path)
+              -> Result<Option<bool>, PlistSpriteAssetLoaderError> {
+    match dict.get(key) {
+        Some(Value::Boolean(b)) => Ok(Some(*b)),
+        Some(_) => Err(PlistSpriteAssetLoaderError::WrongType {
+            path: path.to_path_buf(),
+            key: format!("{}/{}", key_path, key),
+            expected: "boolean",
+        }),
+        None => Ok(None),
     }
+}
+
+fn required<T>(value: Option<T>, key: &str, key_path: &str, path: &Path) -> Result<T, PlistSpriteAssetLoaderError> {
+    value.ok_or_else(|| PlistSpriteAssetLoaderError::MissingField {
+        path: path.to_path_buf(),
+        key: format!("{}/{}", key_path, key),
+    })
+}
+
+/// Where the trimmed sprite sits in its source image, for sheets that only store the offset.
+fn source_color_rect(size: (f32, f32), offset: (f32, f32), source_size: (f32, f32)) -> (f32, f32, f32, f32) {
+    (
+        (source_size.0 - size.0) / 2.0 + offset.0,
+        (source_size.1 - size.1) / 2.0 - offset.1,
+        size.0,
+        size.1,
+    )
+}
+
+/// Zwoptex format 0, plain numbers.
+fn parse_frame_format_0(frame_attr: &Dictionary, key_path: &str, path: &Path) -> Result<SpriteFrame, PlistSpriteAssetLoaderError> {
+    let number = |key: &str| plist_number(frame_attr, key, key_path, path);
+
+    let w = required(number("width")?, "width", key_path, path)?;
+    let h = required(number("height")?, "height", key_path, path)?;
+    let frame = (
+        required(number("x")?, "x", key_path, path)?,
+        required(number("y")?, "y", key_path, path)?,
+        w,
+        h,
+    );
+    let offset = (number("offsetX")?.unwrap_or(0.0), number("offsetY")?.unwrap_or(0.0));
+    let source_size = (
+        number("originalWidth")?.map_or(w, f32::abs),
+        number("originalHeight")?.map_or(h, f32::abs),
+    );
+
+    Ok(SpriteFrame {
+        name: String::new(),
+        frame,
+        offset,
+        rotated: false,
+        source_color_rect: source_color_rect((w, h), offset, source_size),
+        source_size,
+        pivot: None,
+    })
+}
+
+/// Formats 1 and 2, rect strings. Only format 2 can rotate.
+fn parse_frame_format_2(frame_attr: &Dictionary, key_path: &str, format: i64, path: &Path) -> Result<SpriteFrame, PlistSpriteAssetLoaderError> {
+    let frame = required(plist_numbers(frame_attr, "frame", 4, key_path, path)?, "frame", key_path, path)?;
+    let frame = (frame[0], frame[1], frame[2], frame[3]);
+
+    let offset = plist_numbers(frame_attr, "offset", 2, key_path, path)?
+        .map_or((0.0, 0.0), |offset| (offset[0], offset[1]));
+    let source_size = plist_numbers(frame_attr, "sourceSize", 2, key_path, path)?
+        .map_or((frame.2, frame.3), |size| (size[0], size[1]));
+    let source_color_rect = match plist_numbers(frame_attr, "sourceColorRect", 4, key_path, path)? {
+        Some(rect) => (rect[0], rect[1], rect[2], rect[3]),
+        None => source_color_rect((frame.2, frame.3), offset, source_size),
+    };
+    let rotated = match format {
+        2 => plist_bool(frame_attr, "rotated", key_path, path)?.unwrap_or(false),
+        _ => false,
+    };
 
-    if let Some(Value::String(s)) = frame_attr.get("sourceSize") {
-        let s = s.replace("{", "").replace("}", "");
-        let mut source_size = s.split(",");
-        sf.source_size.0 = source_size.next().unwrap().parse::<i32>().unwrap() as f32;
-        sf.source_size.1 = source_size.next().unwrap().parse::<i32>().unwrap() as f32;
+    Ok(SpriteFrame {
+        name: String::new(),
+        frame,
+        offset,
+        rotated,
+        source_color_rect,
+        source_size,
+        pivot: None,
+    })
+}
+
+/// Format 3, written by TexturePacker for cocos2d-x 3.
+fn parse_frame_format_3(frame_attr: &Dictionary, key_path: &str, path: &Path) -> Result<SpriteFrame, PlistSpriteAssetLoaderError> {
+    let size = required(plist_numbers(frame_attr, "spriteSize", 2, key_path, path)?, "spriteSize", key_path, path)?;
+    let size = (size[0], size[1]);
+    let texture_rect = required(plist_numbers(frame_attr, "textureRect", 4, key_path, path)?, "textureRect", key_path, path)?;
+
+    let offset = plist_numbers(frame_attr, "spriteOffset", 2, key_path, path)?
+        .map_or((0.0, 0.0), |offset| (offset[0], offset[1]));
+    let source_size = plist_numbers(frame_attr, "spriteSourceSize", 2, key_path, path)?
+        .map_or(size, |size| (size[0], size[1]));
+    let pivot = plist_numbers(frame_attr, "anchor", 2, key_path, path)?
+        .map(|anchor| (anchor[0], anchor[1]));
+
+    Ok(SpriteFrame {
+        name: String::new(),
+        frame: (texture_rect[0], texture_rect[1], size.0, size.1),
+        offset,
+        rotated: plist_bool(frame_attr, "textureRotated", key_path, path)?.unwrap_or(false),
+        source_color_rect: source_color_rect(size, offset, source_size),
+        source_size,
+        pivot,
+    })
+}
+
+/// The frame and, for format 3, a copy of it for every alias.
+fn parse_frame_from_plist(frame_attr: &Dictionary, frame_name: &str, format: i64, path: &Path)
+                          -> Result<Vec<SpriteFrame>, PlistSpriteAssetLoaderError> {
+    let key_path = format!("frames/{}", frame_name);
+    let mut sf = match format {
+        0 => parse_frame_format_0(frame_attr, &key_path, path)?,
+        1 | 2 => parse_frame_format_2(frame_attr, &key_path, format, path)?,
+        _ => parse_frame_format_3(frame_attr, &key_path, path)?,
+    };
+    sf.name = frame_name.to_string().replace(".png", "");
+
+    let mut sprite_frames = vec![];
+    if let Some(aliases) = frame_attr.get("aliases").filter(|_| format == 3) {
+        let key = format!("{}/aliases", key_path);
+        let aliases = aliases.as_array().ok_or_else(|| PlistSpriteAssetLoaderError::WrongType {
+            path: path.to_path_buf(),
+            key: key.clone(),
+            expected: "array",
+        })?;
+
+        for alias in aliases {
+            let alias = alias.as_string().ok_or_else(|| PlistSpriteAssetLoaderError::WrongType {
+                path: path.to_path_buf(),
+                key: key.clone(),
+                expected: "string",
+            })?;
+            sprite_frames.push(SpriteFrame {
+                name: alias.replace(".png", ""),
+                ..sf.clone()
+            });
+        }
     }
 
-    sf
+    sprite_frames.insert(0, sf);
+    Ok(sprite_frames)
 }
 
 
-fn parse_plist(dict: Value) -> (Vec<SpriteFrame>, String, Vec2) {
+fn parse_plist(dict: Value, path: &Path) -> Result<(Vec<SpriteFrame>, String, Vec2), PlistSpriteAssetLoaderError> {
     let mut sprite_frames = vec![];
 
-    let mut tex_file_name: String = "".to_string();
-    let mut dims: Vec2 = Vec2::new(0.0, 0.0);
+    let dict = dict.into_dictionary().ok_or_else(|| PlistSpriteAssetLoaderError::WrongType {
+        path: path.to_path_buf(),
+        key: "/".to_string(),
+        expected: "dictionary",
+    })?;
 
-    if let Value::Dictionary(dict) = dict {
-        if let Some(Value::Dictionary(frames)) = dict.get("frames") {
-            for (f_name, v) in frames {
-                if let Some(frame_attr) = v.as_dictionary() {
-                    sprite_frames.push(parse_frame_from_plist(frame_attr, f_name));
-                }
-            }
-        }
+    let missing = |key: &str| PlistSpriteAssetLoaderError::MissingField {
+        path: path.to_path_buf(),
+        key: key.to_string(),
+    };
+    let wrong_type = |key: &str, expected: &'static str| PlistSpriteAssetLoaderError::WrongType {
+        path: path.to_path_buf(),
+        key: key.to_string(),
+        expected,
+    };
 
-        if let Some(Value::Dictionary(metadata)) = dict.get("metadata") {
-            if let Some(Value::String(tex_file)) = metadata.get("realTextureFileName") {
-                tex_file_name = tex_file.clone();
-            }
+    // old Zwoptex sheets have no metadata, they are format 0
+    let empty = Dictionary::new();
+    let metadata = match dict.get("metadata") {
+        Some(metadata) => metadata.as_dictionary().ok_or_else(|| wrong_type("metadata", "dictionary"))?,
+        None => &empty,
+    };
 
-            if let Some(Value::String(size)) = metadata.get("size") {
-                let size = size.replace("{", "").replace("}", "");
-                let mut size = size.split(",");
-                let w = size.next().unwrap().parse::<f32>().unwrap();
-                let h = size.next().unwrap().parse::<f32>().unwrap();
-                dims = Vec2::new(w, h);
-            }
+    let format = match metadata.get("format") {
+        Some(format) => format.as_signed_integer().ok_or_else(|| wrong_type("metadata/format", "integer"))?,
+        None => 0,
+    };
+    if !(0..=3).contains(&format) {
+        return Err(PlistSpriteAssetLoaderError::BadValue {
+            path: path.to_path_buf(),
+            key: "metadata/format".to_string(),
+            value: format.to_string(),
+        });
+    }
+
+    let frames = dict.get("frames").ok_or_else(|| missing("frames"))?
+        .as_dictionary().ok_or_else(|| wrong_type("frames", "dictionary"))?;
+    for (f_name, v) in frames {
+        let frame_attr = v.as_dictionary()
+            .ok_or_else(|| wrong_type(&format!("frames/{}", f_name), "dictionary"))?;
+        sprite_frames.extend(parse_frame_from_plist(frame_attr, f_name, format, path)?);
+    }
+
+    let mut tex_file_name = None;
+    for key in ["realTextureFileName", "textureFileName"] {
+        if let Some(name) = metadata.get(key) {
+            let name = name.as_string().ok_or_else(|| wrong_type(&format!("metadata/{}", key), "string"))?;
+            tex_file_name = Some(name.to_string());
+            break;
         }
     }
+    // like cocos, fall back to the png next to the sheet
+    let tex_file_name = match tex_file_name {
+        Some(name) => name,
+        None => path.with_extension("png")
+            .file_name()
+            .ok_or_else(|| missing("metadata/textureFileName"))?
+            .to_string_lossy()
+            .to_string(),
+    };
 
-    (sprite_frames, tex_file_name, dims)
+    let dims = match plist_numbers(metadata, "size", 2, "metadata", path)? {
+        Some(size) => Vec2::new(size[0], size[1]),
+        None => {
+            let texture = dict.get("texture").ok_or_else(|| missing("metadata/size"))?
+                .as_dictionary().ok_or_else(|| wrong_type("texture", "dictionary"))?;
+            Vec2::new(
+                required(plist_number(texture, "width", "texture", path)?, "width", "texture", path)?,
+                required(plist_number(texture, "height", "texture", path)?, "height", "texture", path)?,
+            )
+        }
+    };
+
+    Ok((sprite_frames, tex_file_name, dims))
 }
 
 
 #[derive(Asset, TypePath, Debug, Clone)]
 pub struct PlistSpriteFrameAsset {
     pub frames: Vec<SpriteFrame>,
+    frame_indices: HashMap<String, usize>,
     pub atlas: Handle<TextureAtlasLayout>,
     pub texture: Handle<Image>,
 }
 
+impl PlistSpriteFrameAsset {
+    /// The first of frames with the same name wins.
+    pub(crate) fn new(frames: Vec<SpriteFrame>, atlas: Handle<TextureAtlasLayout>, texture: Handle<Image>) -> Self {
+        let mut frame_indices = HashMap::new();
+        for (index, sf) in frames.iter().enumerate() {
+            frame_indices.entry(sf.name.clone()).or_insert(index);
+        }
+
+        PlistSpriteFrameAsset { frames, frame_indices, atlas, texture }
+    }
+
+    /// Index of the frame in `frames` and in the atlas.
+    pub fn frame_index(&self, name: &str) -> Option<usize> {
+        self.frame_indices.get(name).copied()
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&SpriteFrame> {
+        self.frame_index(name).map(|idx| &self.frames[idx])
+    }
+
+    /// A plain sprite of frame `name` at `transform`, see [`SpriteFrame::local_transform`].
+    pub fn sprite_bundle(&self, name: &str, transform: Transform) -> Option<(SpriteBundle, TextureAtlas)> {
+        let index = self.frame_index(name)?;
+        Some(frame_sprite_bundle(&self.frames[index], index, &self.atlas, &self.texture, transform))
+    }
+}
+
+/// A single frame of a sheet, loaded as `sheet.plist#frame_name`.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct SpriteFrameAsset {
+    pub frame: SpriteFrame,
+    pub index: usize,
+    pub atlas: Handle<TextureAtlasLayout>,
+    pub texture: Handle<Image>,
+}
+
+impl SpriteFrameAsset {
+    /// A plain sprite of the frame at `transform`, see [`SpriteFrame::local_transform`].
+    pub fn sprite_bundle(&self, transform: Transform) -> (SpriteBundle, TextureAtlas) {
+        frame_sprite_bundle(&self.frame, self.index, &self.atlas, &self.texture, transform)
+    }
+}
+
+/// The atlas layout of `frames`, in their order.
+pub(crate) fn atlas_layout(frames: &[SpriteFrame], dims: Vec2) -> TextureAtlasLayout {
+    let mut atlas = TextureAtlasLayout::new_empty(UVec2::new(dims.x as u32, dims.y as u32));
+
+    for sf in frames.iter() {
+        // rotated frames are stored turned by 90 degrees, width and height swap in the texture
+        let rect = match sf.rotated {
+            true => URect {
+                min: UVec2::new(sf.frame.0 as u32, sf.frame.1 as u32),
+                max: UVec2::new(sf.frame.0 as u32 + sf.frame.3 as u32, sf.frame.1 as u32 + sf.frame.2 as u32),
+            },
+            false => URect {
+                min: UVec2::new(sf.frame.0 as u32, sf.frame.1 as u32),
+                max: UVec2::new(sf.frame.0 as u32 + sf.frame.2 as u32, sf.frame.1 as u32 + sf.frame.3 as u32),
+            },
+        };
+        atlas.add_texture(rect);
+    }
+
+    atlas
+}
+
+/// Build the atlas of `frames` and load the texture next to the sheet.
+pub(crate) fn sheet_asset(load_context: &mut LoadContext, frames: Vec<SpriteFrame>, tex_name: &str, dims: Vec2) -> PlistSpriteFrameAsset {
+    let tex_path = load_context.path().parent().unwrap_or(Path::new("")).join(tex_name);
+    let tex_img = load_context.load(tex_path);
+    let atlas_handle = load_context.add_labeled_asset("atlas".to_string(), atlas_layout(&frames, dims));
+    let sheet = PlistSpriteFrameAsset::new(frames, atlas_handle.clone(), tex_img.clone());
+
+    for (index, sf) in sheet.frames.iter().enumerate() {
+        if sheet.frame_index(&sf.name) != Some(index) {
+            warn!("{:?}: duplicate sprite frame {}", load_context.path(), sf.name);
+            continue;
+        }
+
+        if sf.name == "atlas" {
+            warn!("{:?}: sprite frame atlas has no sub asset, the label is taken by the atlas layout", load_context.path());
+            continue;
+        }
+        load_context.add_labeled_asset(sf.name.clone(), SpriteFrameAsset {
+            frame: sf.clone(),
+            index,
+            atlas: atlas_handle.clone(),
+            texture: tex_img.clone(),
+        });
+    }
+
+    sheet
+}
+
 #[non_exhaustive]
 #[derive(Debug, Error)]
 pub enum PlistSpriteAssetLoaderError {
     /// An [IO](std::io) Error
     #[error("Could load asset: {0}")]
     Io(#[from] std::io::Error),
+    #[error("{}: invalid plist: {source}", .path.display())]
+    Plist {
+        path: PathBuf,
+        source: plist::Error,
+    },
+    #[error("{}: {key}: missing field", .path.display())]
+    MissingField {
+        path: PathBuf,
+        key: String,
+    },
+    #[error("{}: {key}: expected {expected}", .path.display())]
+    WrongType {
+        path: PathBuf,
+        key: String,
+        expected: &'static str,
+    },
+    /// A rect, point or size string that can't be parsed
+    #[error("{}: {key}: bad value \"{value}\"", .path.display())]
+    BadValue {
+        path: PathBuf,
+        key: String,
+        value: String,
+    },
 }
 
 
@@ -135,27 +506,11 @@ impl AssetLoader for PlistSpriteAssetLoader {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let dict = plist::from_bytes(&bytes).unwrap();
-            let (sprite_frames, tex_name, dims) = parse_plist(dict);
-            let tex_img = load_context.load(load_context.path().parent().unwrap().join(tex_name));
-            let mut atlas = TextureAtlasLayout::new_empty(UVec2::new(dims.x as u32, dims.y as u32));
-
-            for sf in sprite_frames.iter().by_ref() {
-                let rect = match sf.rotated {
-                    true => URect {
-                        min: UVec2::new(sf.frame.0 as u32, sf.frame.1 as u32),
-                        max: UVec2::new(sf.frame.0 as u32 + sf.frame.3 as u32, sf.frame.1 as u32 + sf.frame.2 as u32),
-                    },
-                    false => URect {
-                        min: UVec2::new(sf.frame.0 as u32, sf.frame.1 as u32),
-                        max: UVec2::new(sf.frame.0 as u32 + sf.frame.2 as u32, sf.frame.1 as u32 + sf.frame.3 as u32),
-                    },
-                };
-                atlas.add_texture(rect);
-            }
-
-            let atlas_handle = load_context.add_labeled_asset("atlas".to_string(), atlas);
-            Ok(PlistSpriteFrameAsset { frames: sprite_frames, atlas: atlas_handle, texture: tex_img })
+            let path = load_context.path().to_path_buf();
+            let dict = plist::from_bytes(&bytes)
+                .map_err(|source| PlistSpriteAssetLoaderError::Plist { path: path.clone(), source })?;
+            let (sprite_frames, tex_name, dims) = parse_plist(dict, &path)?;
+            Ok(sheet_asset(load_context, sprite_frames, &tex_name, dims))
         })
     }
 
@@ -163,3 +518,236 @@ impl AssetLoader for PlistSpriteAssetLoader {
         &["plist"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    fn parse(dict: &str) -> Result<(Vec<SpriteFrame>, String, Vec2), PlistSpriteAssetLoaderError> {
+        let xml = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">{}</plist>"#, dict);
+        let value: Value = plist::from_bytes(xml.as_bytes()).unwrap();
+        parse_plist(value, Path::new("sheets/test.plist"))
+    }
+
+    fn golden_text(frames: &[SpriteFrame], tex_name: &str, dims: Vec2) -> String {
+        let mut text = format!("texture {}\nsize {} {}\n", tex_name, dims.x, dims.y);
+        for sf in frames {
+            writeln!(text, "{} frame {} {} {} {} offset {} {} rotated {} source {} {} {} {} size {} {} pivot {:?}",
+                     sf.name,
+                     sf.frame.0, sf.frame.1, sf.frame.2, sf.frame.3,
+                     sf.offset.0, sf.offset.1,
+                     sf.rotated,
+                     sf.source_color_rect.0, sf.source_color_rect.1, sf.source_color_rect.2, sf.source_color_rect.3,
+                     sf.source_size.0, sf.source_size.1,
+                     sf.pivot).unwrap();
+        }
+        text
+    }
+
+    /// Set `UPDATE_GOLDEN=1` to rewrite `testdata/plist` after an intended change.
+    #[test]
+    fn raw_sheets_match_golden() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let golden_dir = manifest_dir.join("testdata/plist");
+        let mut sheets: Vec<PathBuf> = std::fs::read_dir(manifest_dir.join("../../assets/textures/raw"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "plist"))
+            .collect();
+        sheets.sort();
+        assert!(!sheets.is_empty());
+
+        for sheet in sheets {
+            let value = Value::from_file(&sheet).unwrap();
+            let (frames, tex_name, dims) = parse_plist(value, &sheet).unwrap();
+            let actual = golden_text(&frames, &tex_name, dims);
+
+            let golden = golden_dir.join(sheet.file_stem().unwrap()).with_extension("txt");
+            if std::env::var_os("UPDATE_GOLDEN").is_some() {
+                std::fs::write(&golden, actual).unwrap();
+                continue;
+            }
+
+            let expected = std::fs::read_to_string(&golden)
+                .unwrap_or_else(|e| panic!("{}: {}", golden.display(), e));
+            assert_eq!(actual, expected, "{} doesn't match {}", sheet.display(), golden.display());
+        }
+    }
+
+    #[test]
+    fn format_0() {
+        let (frames, tex_name, dims) = parse(r#"<dict>
+            <key>texture</key>
+            <dict><key>width</key><integer>256</integer><key>height</key><integer>128</integer></dict>
+            <key>frames</key>
+            <dict>
+                <key>a.png</key>
+                <dict>
+                    <key>x</key><real>2</real>
+                    <key>y</key><real>4</real>
+                    <key>width</key><real>10</real>
+                    <key>height</key><real>20</real>
+                    <key>offsetX</key><real>1</real>
+                    <key>offsetY</key><real>-2</real>
+                    <key>originalWidth</key><integer>-30</integer>
+                    <key>originalHeight</key><integer>40</integer>
+                </dict>
+            </dict>
+        </dict>"#).unwrap();
+
+        assert_eq!(tex_name, "test.png");
+        assert_eq!(dims, Vec2::new(256.0, 128.0));
+        let sf = &frames[0];
+        assert_eq!(sf.name, "a");
+        assert_eq!(sf.frame, (2.0, 4.0, 10.0, 20.0));
+        assert_eq!(sf.offset, (1.0, -2.0));
+        assert_eq!(sf.source_size, (30.0, 40.0));
+        assert_eq!(sf.source_color_rect, (11.0, 12.0, 10.0, 20.0));
+        assert!(!sf.rotated);
+    }
+
+    #[test]
+    fn format_1_ignores_rotated() {
+        let (frames, tex_name, _) = parse(r#"<dict>
+            <key>frames</key>
+            <dict>
+                <key>a.png</key>
+                <dict>
+                    <key>frame</key><string>{{2,4},{10,20}}</string>
+                    <key>offset</key><string>{1,-2}</string>
+                    <key>rotated</key><true/>
+                    <key>sourceSize</key><string>{30,40}</string>
+                </dict>
+            </dict>
+            <key>metadata</key>
+            <dict>
+                <key>format</key><integer>1</integer>
+                <key>textureFileName</key><string>sheet.png</string>
+                <key>size</key><string>{64,64}</string>
+            </dict>
+        </dict>"#).unwrap();
+
+        assert_eq!(tex_name, "sheet.png");
+        assert!(!frames[0].rotated);
+        assert_eq!(frames[0].source_color_rect, (11.0, 12.0, 10.0, 20.0));
+    }
+
+    #[test]
+    fn format_3() {
+        let (frames, tex_name, dims) = parse(r#"<dict>
+            <key>frames</key>
+            <dict>
+                <key>a.png</key>
+                <dict>
+                    <key>aliases</key>
+                    <array><string>b.png</string></array>
+                    <key>anchor</key><string>{0.25,0.75}</string>
+                    <key>spriteOffset</key><string>{1,-2}</string>
+                    <key>spriteSize</key><string>{10,20}</string>
+                    <key>spriteSourceSize</key><string>{30,40}</string>
+                    <key>textureRect</key><string>{{2,4},{20,10}}</string>
+                    <key>textureRotated</key><true/>
+                </dict>
+            </dict>
+            <key>metadata</key>
+            <dict>
+                <key>format</key><integer>3</integer>
+                <key>realTextureFileName</key><string>real.png</string>
+                <key>textureFileName</key><string>sheet.png</string>
+                <key>size</key><string>{64,32}</string>
+            </dict>
+        </dict>"#).unwrap();
+
+        assert_eq!(tex_name, "real.png");
+        assert_eq!(dims, Vec2::new(64.0, 32.0));
+        assert_eq!(frames.len(), 2);
+        let sf = &frames[0];
+        assert_eq!(sf.name, "a");
+        assert_eq!(sf.frame, (2.0, 4.0, 10.0, 20.0));
+        assert_eq!(sf.offset, (1.0, -2.0));
+        assert_eq!(sf.source_size, (30.0, 40.0));
+        assert_eq!(sf.source_color_rect, (11.0, 12.0, 10.0, 20.0));
+        assert_eq!(sf.pivot, Some((0.25, 0.75)));
+        assert!(sf.rotated);
+        assert_eq!(frames[1].name, "b");
+        assert_eq!(frames[1].frame, sf.frame);
+    }
+
+    fn hero_frame(frame: (f32, f32, f32, f32), rotated: bool, source_color_rect: (f32, f32, f32, f32),
+                  pivot: Option<(f32, f32)>) -> SpriteFrame {
+        let source_size = (32.0, 48.0);
+        let (x, y, w, h) = source_color_rect;
+        SpriteFrame {
+            name: "hero".to_string(),
+            frame,
+            offset: (x + w / 2.0 - source_size.0 / 2.0, source_size.1 / 2.0 - (y + h / 2.0)),
+            rotated,
+            source_color_rect,
+            source_size,
+            pivot,
+        }
+    }
+
+    /// Where pixel `p` of the untrimmed image (y down from the top left) is shown.
+    fn source_pixel_at(sf: &SpriteFrame, p: Vec2) -> Vec3 {
+        let (x, y, w, h) = sf.source_color_rect;
+        // from the center of the trimmed sprite, y up
+        let rel = Vec2::new(p.x - (x + w / 2.0), (y + h / 2.0) - p.y);
+        // rotated frames are stored turned clockwise in the sheet
+        let in_sheet = if sf.rotated { Vec2::new(rel.y, -rel.x) } else { rel };
+        sf.local_transform().transform_point(in_sheet.extend(0.0))
+    }
+
+    #[test]
+    fn trimmed_and_rotated_frames_land_like_untrimmed() {
+        for pivot in [None, Some((0.25, 0.25))] {
+            let untrimmed = hero_frame((0.0, 0.0, 32.0, 48.0), false, (0.0, 0.0, 32.0, 48.0), pivot);
+            let trimmed = hero_frame((32.0, 0.0, 20.0, 30.0), false, (4.0, 6.0, 20.0, 30.0), pivot);
+            let rotated = hero_frame((0.0, 48.0, 20.0, 30.0), true, (4.0, 6.0, 20.0, 30.0), pivot);
+
+            for p in [Vec2::new(4.0, 6.0), Vec2::new(10.0, 20.0), Vec2::new(23.5, 35.5)] {
+                let expected = source_pixel_at(&untrimmed, p);
+                for sf in [&trimmed, &rotated] {
+                    let actual = source_pixel_at(sf, p);
+                    assert!(actual.abs_diff_eq(expected, 1e-4), "{:?} {:?}: {} != {}", pivot, p, actual, expected);
+                }
+            }
+        }
+
+        // the pivot pixel sits on the origin
+        let rotated = hero_frame((0.0, 48.0, 20.0, 30.0), true, (4.0, 6.0, 20.0, 30.0), Some((0.25, 0.25)));
+        assert!(source_pixel_at(&rotated, Vec2::new(8.0, 36.0)).abs_diff_eq(Vec3::ZERO, 1e-4));
+    }
+
+    #[test]
+    fn local_transform_matches_the_animation_path() {
+        use crate::cocos2d_anim::anim::{Cocos2dAnimDisplay, display_offset};
+
+        let sf = hero_frame((0.0, 48.0, 20.0, 30.0), true, (4.0, 6.0, 20.0, 30.0), Some((0.25, 0.25)));
+        let display = Cocos2dAnimDisplay {
+            name: "hero".to_string(),
+            xy: Vec2::ZERO,
+            scale: Vec2::ONE,
+            skew: Vec2::ZERO,
+            anchor: Vec2::new(0.25, 0.25),
+            sheet: None,
+        };
+
+        assert_eq!(display_offset(&display, &sf), sf.local_transform().translation);
+    }
+
+    #[test]
+    fn unknown_format() {
+        let result = parse(r#"<dict>
+            <key>frames</key><dict/>
+            <key>metadata</key>
+            <dict><key>format</key><integer>4</integer></dict>
+        </dict>"#);
+
+        assert!(matches!(result, Err(PlistSpriteAssetLoaderError::BadValue { .. })));
+    }
+}
@@ -0,0 +1,519 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::{
+    asset::{AssetLoader, io::Reader, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use bevy::asset::AsyncReadExt;
+use bevy::utils::HashMap;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::cocos2d_anim::{AnimationMode, Cocos2dAnimator, Cocos2dAnimatorPlayer};
+use crate::cocos2d_anim::anim::Cocos2dAnimAsset;
+use crate::cocos2d_anim::json::{JsonError, JsonErrorReason, JsonNode};
+
+#[derive(Debug, Clone)]
+pub struct AnimState {
+    pub clip: String,
+    pub mode: AnimationMode,
+    pub crossfade: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimTransition {
+    /// `None` matches every state
+    pub from: Option<String>,
+    pub to: String,
+    /// every parameter must have the given value
+    pub when: Vec<(String, bool)>,
+    /// only leave once the clip of `from` has played through, see [`Cocos2dAnimatorPlayer::finished`]
+    pub on_end: bool,
+}
+
+/// Data driven clip switching, loaded from `.animsm.json`. Gameplay sets bool parameters
+/// on [`AnimStateMachine`], the [`Cocos2dAnimator`] plays the clip of the state the
+/// transitions lead to.
+///
+/// ```json
+/// {
+///   "initial": "born",
+///   "params": { "moving": false, "dead": false },
+///   "states": {
+///     "born": { "clip": "born", "mode": "once" },
+///     "stand": { "clip": "stand", "mode": "loop", "crossfade": 0.1 },
+///     "run": { "clip": "run", "mode": "loop" },
+///     "die": { "clip": "die", "mode": "once" }
+///   },
+///   "transitions": [
+///     { "from": "*", "to": "die", "when": { "dead": true } },
+///     { "from": "born", "to": "stand", "on_end": true },
+///     { "from": "stand", "to": "run", "when": { "moving": true } },
+///     { "from": "run", "to": "stand", "when": { "moving": false } }
+///   ]
+/// }
+/// ```
+/// Transitions are checked in file order, the first one that matches wins.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Cocos2dAnimStateMachine {
+    pub initial: String,
+    pub params: HashMap<String, bool>,
+    pub states: HashMap<String, AnimState>,
+    pub transitions: Vec<AnimTransition>,
+}
+
+impl Cocos2dAnimStateMachine {
+    fn next_state(&self, current: &str, params: &HashMap<String, bool>, ended: bool) -> Option<&str> {
+        self.transitions.iter()
+            .filter(|t| t.to != current)
+            .filter(|t| t.from.as_ref().is_none_or(|from| from == current))
+            .filter(|t| !t.on_end || ended)
+            .find(|t| t.when.iter().all(|(name, value)| {
+                params.get(name).or_else(|| self.params.get(name)).copied().unwrap_or(false) == *value
+            }))
+            .map(|t| t.to.as_str())
+    }
+}
+
+/// Drives the [`Cocos2dAnimator`] of the same entity. Like the animation itself,
+/// the machine has to be loaded before the entity spawns. Clips played on the animator
+/// directly play to their end and then give way to the state's clip.
+#[derive(Component, Debug, Default)]
+pub struct AnimStateMachine {
+    pub machine: Handle<Cocos2dAnimStateMachine>,
+    /// overrides of the machine's default parameters
+    pub params: HashMap<String, bool>,
+    /// clips played instead of the machine's, by state name
+    pub clips: HashMap<String, String>,
+    state: Option<String>,
+}
+
+impl AnimStateMachine {
+    pub fn new(machine: Handle<Cocos2dAnimStateMachine>) -> Self {
+        AnimStateMachine {
+            machine,
+            ..default()
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: bool) {
+        self.params.insert(name.to_string(), value);
+    }
+
+    /// Play `clip` whenever the machine enters `state`, e.g. the attack a unit picked.
+    pub fn set_clip(&mut self, state: &str, clip: &str) {
+        self.clips.insert(state.to_string(), clip.to_string());
+    }
+
+    fn clip<'a>(&'a self, machine: &'a Cocos2dAnimStateMachine, state: &str) -> Option<&'a str> {
+        self.clips.get(state)
+            .or_else(|| machine.states.get(state).map(|state| &state.clip))
+            .map(String::as_str)
+    }
+
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+}
+
+pub(crate) fn drive_state_machine(
+    machines: Res<Assets<Cocos2dAnimStateMachine>>,
+    animations: Res<Assets<Cocos2dAnimAsset>>,
+    mut query: Query<(&mut AnimStateMachine, &mut Cocos2dAnimator, Option<&Cocos2dAnimatorPlayer>)>,
+) {
+    for (mut sm, mut animator, player) in &mut query {
+        let Some(machine) = machines.get(&sm.machine) else {
+            continue;
+        };
+
+        let next = match &sm.state {
+            None => machine.initial.clone(),
+            // a clip switch asked for outside the machine goes first
+            Some(_) if animator.new_anim.is_some() => continue,
+            Some(current) => {
+                let clip = sm.clip(machine, current);
+                let own_clip = player.is_some_and(|p| Some(p.anim_name.as_str()) == clip);
+                // looping and clamped clips never end, they count once they played through
+                let finished = player.is_some_and(|p| p.finished());
+                match machine.next_state(current, &sm.params, finished && own_clip) {
+                    Some(next) => next.to_string(),
+                    // the outside clip is done, back to the clip of the state
+                    None if finished && !own_clip => current.clone(),
+                    None => continue,
+                }
+            }
+        };
+
+        let (Some(state), Some(clip)) = (machine.states.get(&next), sm.clip(machine, &next)) else {
+            warn!("anim state {} not found in {:?}", next, sm.machine);
+            continue;
+        };
+
+        let known = animations.get(&animator.anim_handle)
+            .is_some_and(|asset| asset.animation.contains_key(clip));
+        if known {
+            animator.play(clip, state.mode);
+            animator.crossfade = state.crossfade;
+        } else {
+            warn!("anim state {} plays {}, which is not in {:?}, keep the current clip", next, clip, animator.anim_handle);
+        }
+
+        sm.state = Some(next);
+    }
+}
+
+#[derive(Default)]
+pub struct Cocos2dAnimStateMachineLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum Cocos2dAnimStateMachineLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{}: invalid json: {source}", .path.display())]
+    Syntax {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A missing or malformed field
+    #[error(transparent)]
+    Field(#[from] JsonError),
+}
+
+fn parse_mode(node: &JsonNode) -> Result<AnimationMode, JsonError> {
+    let mode = match node.str()? {
+        "once" => AnimationMode::Once,
+        "loop" => AnimationMode::Loop,
+        "remove" => AnimationMode::Remove,
+        "ping_pong" => AnimationMode::PingPong,
+        "reverse" => AnimationMode::Reverse,
+        "clamp_forever" => AnimationMode::ClampForever,
+        s => {
+            if let Some(n) = s.strip_prefix("loop_").and_then(|n| n.parse().ok()) {
+                AnimationMode::LoopCount(n)
+            } else {
+                return Err(node.error(JsonErrorReason::BadValue(format!("unknown mode {}", s))));
+            }
+        }
+    };
+
+    Ok(mode)
+}
+
+fn parse_params(node: &JsonNode) -> Result<Vec<(String, bool)>, JsonError> {
+    let Some(map) = node.value().as_object() else {
+        return Err(node.error(JsonErrorReason::WrongType("object")));
+    };
+
+    map.keys()
+        .map(|name| Ok((name.clone(), node.get(name)?.bool()?)))
+        .collect()
+}
+
+fn parse_state_machine(root: &JsonNode) -> Result<Cocos2dAnimStateMachine, JsonError> {
+    let initial = root.get("initial")?.str()?.to_string();
+    let params = match root.opt("params") {
+        Some(params) => parse_params(&params)?.into_iter().collect(),
+        None => HashMap::new(),
+    };
+
+    let states_node = root.get("states")?;
+    let Some(state_names) = states_node.value().as_object().map(|m| m.keys()) else {
+        return Err(states_node.error(JsonErrorReason::WrongType("object")));
+    };
+
+    let mut states = HashMap::new();
+    for name in state_names {
+        let state = states_node.get(name)?;
+        let crossfade = match state.opt("crossfade") {
+            Some(secs) => {
+                let duration = Duration::try_from_secs_f32(secs.f32()?)
+                    .map_err(|e| secs.error(JsonErrorReason::BadValue(format!("crossfade: {}", e))))?;
+                Some(duration)
+            }
+            None => None,
+        };
+
+        states.insert(name.clone(), AnimState {
+            clip: state.get("clip")?.str()?.to_string(),
+            mode: match state.opt("mode") {
+                Some(mode) => parse_mode(&mode)?,
+                None => AnimationMode::Once,
+            },
+            crossfade,
+        });
+    }
+
+    if !states.contains_key(&initial) {
+        return Err(root.get("initial")?.error(JsonErrorReason::BadValue(format!("unknown state {}", initial))));
+    }
+
+    let mut transitions = Vec::new();
+    for t in root.get("transitions")?.items()? {
+        let from = match t.get("from")?.str()? {
+            "*" => None,
+            from => Some(from.to_string()),
+        };
+        let to = t.get("to")?.str()?.to_string();
+
+        if let Some(name) = from.iter().chain([&to]).find(|name| !states.contains_key(*name)) {
+            return Err(t.error(JsonErrorReason::BadValue(format!("unknown state {}", name))));
+        }
+
+        transitions.push(AnimTransition {
+            from,
+            to,
+            when: match t.opt("when") {
+                Some(when) => parse_params(&when)?,
+                None => Vec::new(),
+            },
+            on_end: match t.opt("on_end") {
+                Some(on_end) => on_end.bool()?,
+                None => false,
+            },
+        });
+    }
+
+    Ok(Cocos2dAnimStateMachine {
+        initial,
+        params,
+        states,
+        transitions,
+    })
+}
+
+impl AssetLoader for Cocos2dAnimStateMachineLoader {
+    type Asset = Cocos2dAnimStateMachine;
+    type Settings = ();
+    type Error = Cocos2dAnimStateMachineLoaderError;
+
+    fn load<'a>(&'a self, reader: &'a mut Reader,
+                _settings: &'a Self::Settings,
+                load_context: &'a mut LoadContext)
+                -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let path: &Path = load_context.path();
+            let value: Value = serde_json::from_slice(&bytes)
+                .map_err(|source| Cocos2dAnimStateMachineLoaderError::Syntax { path: path.to_path_buf(), source })?;
+
+            Ok(parse_state_machine(&JsonNode::root(path, &value))?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["animsm.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use crate::cocos2d_anim::{Cocos2dAnimPlugin, EventType, OnAnimEvent};
+
+    use super::*;
+
+    fn parse(path: &Path, text: &str) -> Result<Cocos2dAnimStateMachine, JsonError> {
+        let value: Value = serde_json::from_str(text).unwrap();
+        parse_state_machine(&JsonNode::root(path, &value))
+    }
+
+    fn unit_machine() -> Cocos2dAnimStateMachine {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/Resources/Animations/unit.animsm.json");
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        parse(&path, &text).unwrap()
+    }
+
+    fn params(values: &[(&str, bool)]) -> HashMap<String, bool> {
+        values.iter().map(|(name, value)| (name.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn parse_unit_machine() {
+        let machine = unit_machine();
+
+        assert_eq!(machine.initial, "born");
+        assert_eq!(machine.params.get("moving"), Some(&false));
+        assert_eq!(machine.params.get("attacking"), Some(&false));
+        assert_eq!(machine.states.len(), 5);
+        assert_eq!(machine.states["run"].clip, "run");
+        assert_eq!(machine.states["run"].mode, AnimationMode::Loop);
+        assert_eq!(machine.states["die"].mode, AnimationMode::Once);
+        assert_eq!(machine.transitions[0].from, None);
+        assert!(machine.transitions[1].on_end);
+    }
+
+    #[test]
+    fn born_waits_for_its_clip() {
+        let machine = unit_machine();
+
+        assert_eq!(machine.next_state("born", &params(&[]), false), None);
+        assert_eq!(machine.next_state("born", &params(&[]), true), Some("stand"));
+        assert_eq!(machine.next_state("born", &params(&[("moving", true)]), true), Some("run"));
+    }
+
+    #[test]
+    fn moving_switches_stand_and_run() {
+        let machine = unit_machine();
+
+        assert_eq!(machine.next_state("stand", &params(&[]), false), None);
+        assert_eq!(machine.next_state("stand", &params(&[("moving", true)]), false), Some("run"));
+        assert_eq!(machine.next_state("run", &params(&[("moving", true)]), false), None);
+        assert_eq!(machine.next_state("run", &params(&[("moving", false)]), false), Some("stand"));
+    }
+
+    #[test]
+    fn dead_wins_from_every_state() {
+        let machine = unit_machine();
+        let dead = params(&[("dead", true), ("moving", true)]);
+
+        for state in ["born", "stand", "run", "attack"] {
+            assert_eq!(machine.next_state(state, &dead, false), Some("die"));
+        }
+        assert_eq!(machine.next_state("die", &dead, true), None);
+    }
+
+    #[test]
+    fn attacks_give_way_once_played() {
+        let machine = unit_machine();
+        let attacking = params(&[("attacking", true)]);
+
+        assert_eq!(machine.next_state("stand", &attacking, false), Some("attack"));
+        assert_eq!(machine.next_state("run", &params(&[("attacking", true), ("moving", true)]), false), Some("attack"));
+        assert_eq!(machine.next_state("attack", &attacking, false), None);
+        assert_eq!(machine.next_state("attack", &params(&[]), true), Some("stand"));
+        assert_eq!(machine.next_state("attack", &params(&[("moving", true)]), true), Some("run"));
+    }
+
+    #[test]
+    fn clip_overrides_the_state_clip() {
+        let machine = unit_machine();
+        let mut sm = AnimStateMachine::default();
+
+        assert_eq!(sm.clip(&machine, "attack"), Some("attack"));
+        sm.set_clip("attack", "slash");
+        assert_eq!(sm.clip(&machine, "attack"), Some("slash"));
+        assert_eq!(sm.clip(&machine, "stand"), Some("stand"));
+        assert_eq!(sm.clip(&machine, "fly"), None);
+    }
+
+    /// The plugin's systems in their own order: born plays through, then the unit stands
+    /// and runs once it moves.
+    #[test]
+    fn unit_goes_from_born_to_stand_to_run() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin {
+            file_path: "testdata".to_string(),
+            ..default()
+        }, Cocos2dAnimPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)));
+
+        let anim: Handle<Cocos2dAnimAsset> = app.world().resource::<AssetServer>().load("state_machine/unit.ske.json");
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<Assets<Cocos2dAnimAsset>>().contains(&anim) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(app.world().resource::<Assets<Cocos2dAnimAsset>>().contains(&anim), "unit.ske.json didn't load in time");
+
+        let machine = app.world_mut().resource_mut::<Assets<Cocos2dAnimStateMachine>>().add(unit_machine());
+        let unit = app.world_mut().spawn((
+            SpatialBundle::default(),
+            Cocos2dAnimator {
+                anim_handle: anim,
+                ..default()
+            },
+            AnimStateMachine::new(machine),
+        )).id();
+        // no event_channel, the entity is still told born ended
+        let born_ends = Arc::new(AtomicUsize::new(0));
+        let counter = born_ends.clone();
+        app.world_mut().entity_mut(unit).observe(move |trigger: Trigger<OnAnimEvent>, players: Query<&Cocos2dAnimatorPlayer>| {
+            let born = players.get(trigger.entity()).is_ok_and(|p| p.anim_name == "born");
+            if born && matches!(trigger.event().evt_type, EventType::End) {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        let clip = |app: &App| app.world().get::<Cocos2dAnimatorPlayer>(unit).map(|p| p.anim_name.clone());
+        let state = |app: &App| app.world().get::<AnimStateMachine>(unit).and_then(|sm| sm.state().map(str::to_string));
+
+        app.update();
+        assert_eq!(clip(&app).as_deref(), Some("born"));
+        assert!(app.world().get::<Cocos2dAnimator>(unit).unwrap().new_anim.is_none());
+
+        // 3 frames of born, a step past its end, then the switch
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(state(&app).as_deref(), Some("stand"));
+        assert_eq!(clip(&app).as_deref(), Some("stand"));
+        assert_eq!(born_ends.load(Ordering::Relaxed), 1);
+
+        app.world_mut().get_mut::<AnimStateMachine>(unit).unwrap().set("moving", true);
+        app.update();
+        app.update();
+        assert_eq!(state(&app).as_deref(), Some("run"));
+        assert_eq!(clip(&app).as_deref(), Some("run"));
+    }
+
+    #[test]
+    fn unknown_state_is_an_error() {
+        let path = Path::new("bad.animsm.json");
+        let err = parse(path, r#"{
+            "initial": "stand",
+            "states": { "stand": { "clip": "stand" } },
+            "transitions": [{ "from": "stand", "to": "run" }]
+        }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown state run"), "{}", err);
+
+        let err = parse(path, r#"{ "initial": "run", "states": {}, "transitions": [] }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown state run"), "{}", err);
+    }
+
+    #[test]
+    fn parse_modes() {
+        let path = Path::new("modes.animsm.json");
+        let machine = parse(path, r#"{
+            "initial": "a",
+            "states": {
+                "a": { "clip": "a", "mode": "loop_3", "crossfade": 0.25 },
+                "b": { "clip": "b", "mode": "clamp_forever" }
+            },
+            "transitions": []
+        }"#).unwrap();
+
+        assert_eq!(machine.states["a"].mode, AnimationMode::LoopCount(3));
+        assert_eq!(machine.states["a"].crossfade, Some(Duration::from_millis(250)));
+        assert_eq!(machine.states["b"].mode, AnimationMode::ClampForever);
+        assert_eq!(machine.states["b"].crossfade, None);
+
+        let err = parse(path, r#"{
+            "initial": "a",
+            "states": { "a": { "clip": "a", "mode": "sometimes" } },
+            "transitions": []
+        }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown mode sometimes"), "{}", err);
+
+        let err = parse(path, r#"{
+            "initial": "a",
+            "states": { "a": { "clip": "a", "crossfade": -1 } },
+            "transitions": []
+        }"#).unwrap_err();
+        assert!(err.to_string().contains("crossfade"), "{}", err);
+    }
+}
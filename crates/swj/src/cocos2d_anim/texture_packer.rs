@@ -0,0 +1,275 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    asset::{AssetLoader, io::Reader, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy::asset::AsyncReadExt;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::cocos2d_anim::json::{JsonError, JsonErrorReason, JsonNode};
+use crate::cocos2d_anim::sprite_sheet::{PlistSpriteFrameAsset, sheet_asset, SpriteFrame};
+
+/// Loads TexturePacker sheets exported as "JSON (Hash)" or "JSON (Array)" into the
+/// same [`PlistSpriteFrameAsset`] as cocos plists, so animations can list either in
+/// `config_file_path`. Save them as `.tp.json`, plain `.json` is taken by the config loader.
+#[derive(Default)]
+pub struct TexturePackerSpriteAssetLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum TexturePackerSpriteAssetLoaderError {
+    /// An [IO](std::io) Error
+    #[error("Could not load asset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{}: invalid json: {source}", .path.display())]
+    Syntax {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// A missing or malformed field
+    #[error(transparent)]
+    Field(#[from] JsonError),
+}
+
+fn json_rect(node: &JsonNode) -> Result<(f32, f32, f32, f32), JsonError> {
+    Ok((node.get("x")?.f32()?, node.get("y")?.f32()?, node.get("w")?.f32()?, node.get("h")?.f32()?))
+}
+
+fn json_size(node: &JsonNode) -> Result<(f32, f32), JsonError> {
+    Ok((node.get("w")?.f32()?, node.get("h")?.f32()?))
+}
+
+fn parse_frame(frame_attr: &JsonNode, frame_name: &str) -> Result<SpriteFrame, JsonError> {
+    let frame = json_rect(&frame_attr.get("frame")?)?;
+    let rotated = match frame_attr.opt("rotated") {
+        Some(rotated) => rotated.bool()?,
+        None => false,
+    };
+    let trimmed = match frame_attr.opt("trimmed") {
+        Some(trimmed) => trimmed.bool()?,
+        None => false,
+    };
+
+    // `frame` has the size of the unrotated sprite, like cocos plists
+    let source_size = match frame_attr.opt("sourceSize") {
+        Some(size) => json_size(&size)?,
+        None => (frame.2, frame.3),
+    };
+    let source_rect = match frame_attr.opt("spriteSourceSize") {
+        Some(rect) if trimmed => json_rect(&rect)?,
+        _ => (0.0, 0.0, frame.2, frame.3),
+    };
+
+    // cocos offsets are from the source center to the trimmed center, y up
+    let offset = (
+        source_rect.0 + source_rect.2 / 2.0 - source_size.0 / 2.0,
+        source_size.1 / 2.0 - (source_rect.1 + source_rect.3 / 2.0),
+    );
+
+    // TexturePacker pivots are y down
+    let pivot = match frame_attr.opt("pivot") {
+        Some(pivot) => Some((pivot.get("x")?.f32()?, 1.0 - pivot.get("y")?.f32()?)),
+        None => None,
+    };
+
+    Ok(SpriteFrame {
+        name: frame_name.replace(".png", ""),
+        frame,
+        offset,
+        rotated,
+        source_color_rect: source_rect,
+        source_size,
+        pivot,
+    })
+}
+
+fn parse_sheet(root: &JsonNode) -> Result<(Vec<SpriteFrame>, String, Vec2), JsonError> {
+    let frames_node = root.get("frames")?;
+    let mut sprite_frames = vec![];
+
+    match frames_node.value() {
+        Value::Object(map) => {
+            for name in map.keys() {
+                sprite_frames.push(parse_frame(&frames_node.get(name)?, name)?);
+            }
+        }
+        Value::Array(_) => {
+            for frame_attr in frames_node.items()? {
+                let name = frame_attr.get("filename")?.str()?;
+                sprite_frames.push(parse_frame(&frame_attr, name)?);
+            }
+        }
+        _ => return Err(frames_node.error(JsonErrorReason::WrongType("object or array"))),
+    }
+
+    let meta = root.get("meta")?;
+    let tex_file_name = meta.get("image")?.str()?.to_string();
+    let (w, h) = json_size(&meta.get("size")?)?;
+
+    Ok((sprite_frames, tex_file_name, Vec2::new(w, h)))
+}
+
+impl AssetLoader for TexturePackerSpriteAssetLoader {
+    type Asset = PlistSpriteFrameAsset;
+    type Settings = ();
+    type Error = TexturePackerSpriteAssetLoaderError;
+
+    fn load<'a>(&'a self, reader: &'a mut Reader,
+                _settings: &'a Self::Settings, load_context: &'a mut LoadContext)
+                -> BoxedFuture<'a, Result<Self::Asset, Self::Error>>
+    {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let path: &Path = load_context.path();
+            let value: Value = serde_json::from_slice(&bytes)
+                .map_err(|source| TexturePackerSpriteAssetLoaderError::Syntax { path: path.to_path_buf(), source })?;
+            let (sprite_frames, tex_name, dims) = parse_sheet(&JsonNode::root(path, &value))?;
+
+            Ok(sheet_asset(load_context, sprite_frames, &tex_name, dims))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tp.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::asset::LoadState;
+
+    use crate::cocos2d_anim::anim::{Cocos2dAnimAsset, Cocos2dAnimAssetLoader};
+    use crate::cocos2d_anim::dragon_bones::{DragonBonesAssetLoader, DragonBonesAtlasLoader};
+    use crate::cocos2d_anim::sprite_sheet::SpriteFrameAsset;
+
+    use super::*;
+
+    fn testdata(name: &str) -> (Vec<SpriteFrame>, String, Vec2) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/texture_packer").join(name);
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let value: Value = serde_json::from_str(&text).unwrap();
+        parse_sheet(&JsonNode::root(&path, &value)).unwrap()
+    }
+
+    fn frame<'a>(frames: &'a [SpriteFrame], name: &str) -> &'a SpriteFrame {
+        frames.iter().find(|sf| sf.name == name).unwrap_or_else(|| panic!("no frame {}", name))
+    }
+
+    fn assert_hero_sheet(file: &str) {
+        let (frames, tex_name, dims) = testdata(file);
+        assert_eq!(tex_name, "hero.png");
+        assert_eq!(dims, Vec2::new(64.0, 128.0));
+        assert_eq!(frames.len(), 3);
+
+        let idle = frame(&frames, "idle");
+        assert_eq!(idle.frame, (0.0, 0.0, 32.0, 48.0));
+        assert_eq!(idle.offset, (0.0, 0.0));
+        assert_eq!(idle.source_color_rect, (0.0, 0.0, 32.0, 48.0));
+        assert!(!idle.rotated);
+        assert_eq!(idle.pivot, None);
+
+        // trimmed 4 from the left and 6 from the top of 32x48
+        let hit = frame(&frames, "hit");
+        assert_eq!(hit.source_color_rect, (4.0, 6.0, 20.0, 30.0));
+        assert_eq!(hit.source_size, (32.0, 48.0));
+        assert_eq!(hit.offset, (-2.0, 3.0));
+        assert!(!hit.rotated);
+
+        let swing = frame(&frames, "swing");
+        assert!(swing.rotated);
+        assert_eq!(swing.frame, (0.0, 48.0, 40.0, 16.0));
+        assert_eq!(swing.offset, (-2.0, -2.0));
+        assert_eq!(swing.source_size, (48.0, 32.0));
+        assert_eq!(swing.pivot, Some((0.25, 0.25)));
+    }
+
+    #[test]
+    fn hash_sheet() {
+        assert_hero_sheet("hero_hash.tp.json");
+    }
+
+    #[test]
+    fn array_sheet() {
+        assert_hero_sheet("hero_array.tp.json");
+
+        // the array keeps the file order
+        let (frames, _, _) = testdata("hero_array.tp.json");
+        let names: Vec<&str> = frames.iter().map(|sf| sf.name.as_str()).collect();
+        assert_eq!(names, ["idle", "hit", "swing"]);
+    }
+
+    #[test]
+    fn untrimmed_frames_ignore_sprite_source_size() {
+        let path = Path::new("untrimmed.tp.json");
+        let value = serde_json::json!({
+            "frame": { "x": 0, "y": 0, "w": 10, "h": 20 },
+            "trimmed": false,
+            "spriteSourceSize": { "x": 3, "y": 3, "w": 4, "h": 4 },
+            "sourceSize": { "w": 10, "h": 20 }
+        });
+        let sf = parse_frame(&JsonNode::root(path, &value), "a.png").unwrap();
+        assert_eq!(sf.name, "a");
+        assert_eq!(sf.source_color_rect, (0.0, 0.0, 10.0, 20.0));
+        assert_eq!(sf.offset, (0.0, 0.0));
+    }
+
+    #[test]
+    fn bad_frames_are_errors() {
+        let path = Path::new("bad.tp.json");
+        let value = serde_json::json!({ "frames": 3, "meta": {} });
+        let err = parse_sheet(&JsonNode::root(path, &value)).unwrap_err();
+        assert!(err.to_string().contains("expected object or array"), "{}", err);
+
+        let value = serde_json::json!({ "frames": [{ "frame": { "x": 0, "y": 0, "w": 1, "h": 1 } }], "meta": {} });
+        let err = parse_sheet(&JsonNode::root(path, &value)).unwrap_err();
+        assert!(err.to_string().contains("/frames/0/filename"), "{}", err);
+    }
+
+    /// `.tp.json` shares its asset type with the `.tex.json` loader, an ExportJson
+    /// listing one must still get the TexturePacker loader.
+    #[test]
+    fn export_json_loads_a_tp_json_sheet() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin {
+            file_path: "testdata".to_string(),
+            ..default()
+        }))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<SpriteFrameAsset>()
+            .init_asset::<PlistSpriteFrameAsset>()
+            .init_asset::<Cocos2dAnimAsset>()
+            .init_asset_loader::<TexturePackerSpriteAssetLoader>()
+            .init_asset_loader::<DragonBonesAtlasLoader>()
+            .register_asset_loader(Cocos2dAnimAssetLoader::default())
+            .register_asset_loader(DragonBonesAssetLoader::default());
+
+        let handle: Handle<Cocos2dAnimAsset> = app.world().resource::<AssetServer>().load("texture_packer/hero.ExportJson");
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<Assets<Cocos2dAnimAsset>>().contains(&handle) {
+                break;
+            }
+            let state = app.world().resource::<AssetServer>().load_state(&handle);
+            assert!(matches!(state, LoadState::NotLoaded | LoadState::Loading), "hero.ExportJson: {:?}", state);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let anims = app.world().resource::<Assets<Cocos2dAnimAsset>>();
+        let asset = anims.get(&handle).expect("hero.ExportJson didn't load in time");
+        let attack = &asset.animation["attack"];
+        let body = &attack.layers["body"];
+        assert_eq!(body.len(), 2);
+        // the rotated frame of the sheet keeps its flag, its index is the one in the sheet
+        assert!(!body[0].rotated);
+        assert!(body[1].rotated);
+        assert_eq!(body[1].sprite_idx, 2);
+    }
+}
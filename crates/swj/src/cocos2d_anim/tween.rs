@@ -0,0 +1,173 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Easing curves of cocostudio `twE`, in the order of cocos2d `tweenfunc::TweenType`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweenEasing {
+    Linear,
+    SineIn,
+    SineOut,
+    SineInOut,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+/// `TWEEN_EASING_MAX`, cocostudio writes it for frames that must not be tweened.
+const TWEEN_EASING_MAX: i64 = 10000;
+
+impl TweenEasing {
+    /// Map a `twE` value to an easing, `None` means the frame holds until the next keyframe.
+    pub fn from_cocos(value: i64) -> Option<TweenEasing> {
+        use TweenEasing::*;
+
+        let easing = match value {
+            TWEEN_EASING_MAX => return None,
+            // custom bezier easing (-1) is not supported, fall back to linear
+            i64::MIN..=0 => Linear,
+            1 => SineIn,
+            2 => SineOut,
+            3 => SineInOut,
+            4 => QuadIn,
+            5 => QuadOut,
+            6 => QuadInOut,
+            7 => CubicIn,
+            8 => CubicOut,
+            9 => CubicInOut,
+            10 => QuartIn,
+            11 => QuartOut,
+            12 => QuartInOut,
+            13 => QuintIn,
+            14 => QuintOut,
+            15 => QuintInOut,
+            16 => ExpoIn,
+            17 => ExpoOut,
+            18 => ExpoInOut,
+            19 => CircIn,
+            20 => CircOut,
+            21 => CircInOut,
+            22 => ElasticIn,
+            23 => ElasticOut,
+            24 => ElasticInOut,
+            25 => BackIn,
+            26 => BackOut,
+            27 => BackInOut,
+            28 => BounceIn,
+            29 => BounceOut,
+            30 => BounceInOut,
+            _ => Linear,
+        };
+
+        Some(easing)
+    }
+
+    /// Eased progress for `t` in `[0, 1]`.
+    pub fn ease(&self, t: f32) -> f32 {
+        use TweenEasing::*;
+
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Linear => t,
+            SineIn => 1.0 - (t * FRAC_PI_2).cos(),
+            SineOut => (t * FRAC_PI_2).sin(),
+            SineInOut => -0.5 * ((PI * t).cos() - 1.0),
+            QuadIn => t * t,
+            QuadOut => -t * (t - 2.0),
+            QuadInOut => in_out(t, |t| t * t),
+            CubicIn => t.powi(3),
+            CubicOut => 1.0 - (1.0 - t).powi(3),
+            CubicInOut => in_out(t, |t| t.powi(3)),
+            QuartIn => t.powi(4),
+            QuartOut => 1.0 - (1.0 - t).powi(4),
+            QuartInOut => in_out(t, |t| t.powi(4)),
+            QuintIn => t.powi(5),
+            QuintOut => 1.0 - (1.0 - t).powi(5),
+            QuintInOut => in_out(t, |t| t.powi(5)),
+            ExpoIn => expo_in(t),
+            ExpoOut => 1.0 - expo_in(1.0 - t),
+            ExpoInOut => in_out(t, expo_in),
+            CircIn => 1.0 - (1.0 - t * t).sqrt(),
+            CircOut => (1.0 - (t - 1.0) * (t - 1.0)).sqrt(),
+            CircInOut => in_out(t, |t| 1.0 - (1.0 - t * t).sqrt()),
+            ElasticIn => elastic_in(t),
+            ElasticOut => 1.0 - elastic_in(1.0 - t),
+            ElasticInOut => in_out(t, elastic_in),
+            BackIn => back_in(t),
+            BackOut => 1.0 - back_in(1.0 - t),
+            BackInOut => in_out(t, back_in),
+            BounceIn => 1.0 - bounce_out(1.0 - t),
+            BounceOut => bounce_out(t),
+            BounceInOut => in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+fn in_out(t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) / 2.0
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) / 2.0
+    }
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else {
+        2f32.powf(10.0 * (t - 1.0))
+    }
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+
+    let period = 0.3;
+    let s = period / 4.0;
+    let t = t - 1.0;
+    -(2f32.powf(10.0 * t) * ((t - s) * PI * 2.0 / period).sin())
+}
+
+fn back_in(t: f32) -> f32 {
+    let overshoot = 1.70158;
+    t * t * ((overshoot + 1.0) * t - overshoot)
+}
+
+fn bounce_out(t: f32) -> f32 {
+    if t < 1.0 / 2.75 {
+        7.5625 * t * t
+    } else if t < 2.0 / 2.75 {
+        let t = t - 1.5 / 2.75;
+        7.5625 * t * t + 0.75
+    } else if t < 2.5 / 2.75 {
+        let t = t - 2.25 / 2.75;
+        7.5625 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / 2.75;
+        7.5625 * t * t + 0.984375
+    }
+}
@@ -28,7 +28,7 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameStates>()
             .add_plugins((
-                Cocos2dAnimPlugin,
+                Cocos2dAnimPlugin::default(),
                 // PfControllerPlugin,
                 SpriteDebugPlugin,
                 ResourcePlugin,
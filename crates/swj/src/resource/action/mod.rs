@@ -5,7 +5,7 @@ use bevy::prelude::*;
 
 use crate::game::GameStates::Playing;
 use crate::resource::action::melee::{MeleeAct, MeleeInfo};
-use crate::resource::action::projectile::{ProjectileAct, ProjectileInfo};
+use crate::resource::action::projectile::{PROJECTILE_ANIM_STATE_MACHINE, ProjectileAct, ProjectileInfo};
 use crate::resource::ResourcePath;
 use crate::unit::UnitHealth;
 
@@ -85,6 +85,7 @@ impl Action {
                 base_damage: info.base_damage,
                 damage_factor: info.damage_factor,
                 bullet_animation_handle: asset_server.load(info.bullet_animation_name.anim_path()),
+                bullet_anim_state_handle: asset_server.load(PROJECTILE_ANIM_STATE_MACHINE),
                 fly_speed: info.fly_speed,
                 bullet_height_factor: info.bullet_height_factor,
                 bullet_height_base: info.bullet_height_base,
@@ -2,7 +2,8 @@ use bevy::color::palettes::basic::RED;
 use bevy::prelude::*;
 use serde_json::Value;
 use crate::cocos2d_anim::anim::Cocos2dAnimAsset;
-use crate::cocos2d_anim::{AnimationMode, Cocos2dAnimator};
+use crate::cocos2d_anim::pool::DespawnAnimatorExt;
+use crate::cocos2d_anim::state_machine::{AnimStateMachine, Cocos2dAnimStateMachine};
 
 use crate::game::GameStates::PrepareLoad;
 use crate::game::OrderElement;
@@ -12,6 +13,10 @@ use crate::unit::{get_unit_aim_body_offset, Unit, UnitState, UnitType};
 
 pub struct ProjectilePlugin;
 
+/// Flies, then plays `missEnd` once the `missed` parameter is set, preloaded with the bullets.
+pub const PROJECTILE_ANIM_STATE_MACHINE: &str = "Resources/Animations/projectile.animsm.json";
+const PROJECTILE_ANIM_MISSED: &str = "missed";
+
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
         app
@@ -68,13 +73,13 @@ fn projectile_finish_fly(
     mut commands: Commands,
     mut rm_fly: RemovedComponents<ProjectileFly>,
     mut damage_writer: EventWriter<DamageEvent>,
-    mut query: Query<(Entity, &Projectile, &mut Transform, &mut Cocos2dAnimator, &ProjectileToFixedTarget, &mut OrderElement), (Without<ProjectileFly>, Without<UnitState>)>,
+    mut query: Query<(Entity, &Projectile, &mut Transform, &mut AnimStateMachine, &ProjectileToFixedTarget, &mut OrderElement), (Without<ProjectileFly>, Without<UnitState>)>,
     unit_query: Query<(&Unit, &Transform), With<UnitState>>,
     query_all: Query<&Projectile>,
 ) {
     let projectile_count = query_all.iter().count();
     for entity in rm_fly.read() {
-        if let Ok((entity, projectile, mut transform, mut animator, fixed_target, mut order)) = query.get_mut(entity) {
+        if let Ok((entity, projectile, mut transform, mut anim_state, fixed_target, mut order)) = query.get_mut(entity) {
             let pos = transform.translation.truncate();
             order.offset = None;
 
@@ -91,12 +96,11 @@ fn projectile_finish_fly(
                     if unit_pos.distance(pos) > projectile.tolerance {
                         //miss
                         if projectile_count > 2000 {
-                            commands.entity(entity).despawn_recursive();
+                            commands.entity(entity).despawn_animator();
                             continue;
                         }
 
-                        animator.new_anim = Some("missEnd".to_string());
-                        animator.mode = AnimationMode::Once;
+                        anim_state.set(PROJECTILE_ANIM_MISSED, true);
 
                         commands.entity(entity)
                             .insert(ProjectileMiss(Timer::from_seconds(5.5, TimerMode::Once)));
@@ -107,7 +111,7 @@ fn projectile_finish_fly(
                             target: target_entity,
                             damage: projectile.damage,
                         });
-                        commands.entity(entity).despawn_recursive();
+                        commands.entity(entity).despawn_animator();
                     }
                 }
                 TargetType::Position(target_pos) => {
@@ -129,7 +133,7 @@ fn projectile_miss(
     for (entity, mut miss) in query.iter_mut() {
         miss.tick(time.delta());
         if miss.just_finished() {
-            commands.entity(entity).despawn_recursive();
+            commands.entity(entity).despawn_animator();
         }
     }
 }
@@ -268,6 +272,7 @@ pub struct ProjectileAct {
     pub base_damage: f32,
     pub damage_factor: f32,
     pub bullet_animation_handle: Handle<Cocos2dAnimAsset>,
+    pub bullet_anim_state_handle: Handle<Cocos2dAnimStateMachine>,
     pub fly_speed: f32,
     pub bullet_height_factor: f32,
     pub bullet_height_base: f32,
@@ -1,6 +1,8 @@
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
 
+use crate::cocos2d_anim::CocoAnim2dAnimatorLayer;
+use crate::cocos2d_anim::pool::{Cocos2dLayerPool, DespawnAnimatorExt};
 use crate::resource::action::projectile::Projectile;
 use crate::unit::Unit;
 
@@ -17,6 +19,8 @@ impl Plugin for SpriteDebugPlugin {
             .add_systems(Update, (
                 update_sprite_count::<Unit>,
                 update_sprite_count::<Projectile>,
+                update_layer_count,
+                update_layer_pool,
                 update_fps,
                 btn_reset_game,
                 btn_add_unit,
@@ -42,9 +46,26 @@ impl<T> Default for CountTag<T> {
 #[derive(Component)]
 struct TextFps;
 
+#[derive(Component)]
+struct TextLayerPool;
+
 #[derive(Component)]
 struct TextResetGame;
 
+/// A label and a value section, the value is set by the update systems.
+fn counter_text(label: &str, asset_server: &AssetServer) -> TextBundle {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    TextBundle::from_sections([
+        TextSection::new(label, style.clone()),
+        TextSection::from_style(style),
+    ])
+}
+
 fn setup_ui(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -61,63 +82,11 @@ fn setup_ui(
             ..Default::default()
         })
         .with_children(|parent| {
-            parent.spawn((
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "Unit Count: ",
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                            font_size: 20.0,
-                            color: Color::WHITE,
-                        },
-                    ),
-                    TextSection::from_style(TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                        font_size: 20.0,
-                        color: Color::WHITE,
-                    }),
-                ]),
-                CountTag::<Unit>::default(),
-            ));
-
-
-            parent.spawn((
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "Projectile Count: ",
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                            font_size: 20.0,
-                            color: Color::WHITE,
-                        },
-                    ),
-                    TextSection::from_style(TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                        font_size: 20.0,
-                        color: Color::WHITE,
-                    }),
-                ]),
-                CountTag::<Projectile>::default(),
-            ));
-
-            parent.spawn((
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "FPS: ",
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                            font_size: 20.0,
-                            color: Color::WHITE,
-                        },
-                    ),
-                    TextSection::from_style(TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                        font_size: 20.0,
-                        color: Color::WHITE,
-                    }),
-                ]),
-                TextFps,
-            ));
+            parent.spawn((counter_text("Unit Count: ", &asset_server), CountTag::<Unit>::default()));
+            parent.spawn((counter_text("Projectile Count: ", &asset_server), CountTag::<Projectile>::default()));
+            parent.spawn((counter_text("Layer Count: ", &asset_server), CountTag::<CocoAnim2dAnimatorLayer>::default()));
+            parent.spawn((counter_text("Pooled Layers: ", &asset_server), TextLayerPool));
+            parent.spawn((counter_text("FPS: ", &asset_server), TextFps));
         });
 
     commands.spawn(NodeBundle {
@@ -240,6 +209,25 @@ fn update_sprite_count<T: Component>(
     text.sections[1].value = num.to_string();
 }
 
+/// Layers in use, the pooled ones are shown on their own.
+fn update_layer_count(
+    mut query_ui: Query<&mut Text, With<CountTag<CocoAnim2dAnimatorLayer>>>,
+    query_layer: Query<(), With<CocoAnim2dAnimatorLayer>>,
+    pool: Res<Cocos2dLayerPool>,
+) {
+    let num = query_layer.iter().count().saturating_sub(pool.len());
+    let mut text = query_ui.single_mut();
+    text.sections[1].value = num.to_string();
+}
+
+fn update_layer_pool(
+    mut query_ui: Query<&mut Text, With<TextLayerPool>>,
+    pool: Res<Cocos2dLayerPool>,
+) {
+    let mut text = query_ui.single_mut();
+    text.sections[1].value = pool.len().to_string();
+}
+
 
 fn btn_reset_game(
     mut commands: Commands,
@@ -250,7 +238,7 @@ fn btn_reset_game(
     let interaction = btn_query.get_single();
     if let Ok(Interaction::Pressed) = interaction {
         for entity in query_unit.iter() {
-            commands.entity(entity).despawn_recursive();
+            commands.entity(entity).despawn_animator();
         }
 
         // app_exit_events.send(AppExit);
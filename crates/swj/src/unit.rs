@@ -11,9 +11,10 @@ use serde_json::Value;
 
 use swj_utils::unit_team_system;
 
-use crate::AnimChannel;
-use crate::cocos2d_anim::{AnimationFaceDir, AnimationMode, AnimEvent, Cocos2dAnimator, Cocos2dAnimatorPlayer, EventType};
+use crate::cocos2d_anim::{AnimationFaceDir, Cocos2dAnimator, Cocos2dAnimatorPlayer, Cocos2dAnimSet, EventType, OnAnimEvent};
 use crate::cocos2d_anim::anim::FrameEvent;
+use crate::cocos2d_anim::pool::DespawnAnimatorExt;
+use crate::cocos2d_anim::state_machine::AnimStateMachine;
 use crate::game::GameStates::{Playing, PrepareLoad};
 use crate::game::OrderElement;
 use crate::resource::{ConfigResource, ConfigResourceParse};
@@ -25,6 +26,14 @@ use crate::unit::UnitState::Moving;
 
 pub struct UnitPlugin;
 
+/// Clip switching of every unit, preloaded with the unit animations.
+pub const UNIT_ANIM_STATE_MACHINE: &str = "Resources/Animations/unit.animsm.json";
+const UNIT_ANIM_MOVING: &str = "moving";
+const UNIT_ANIM_ATTACKING: &str = "attacking";
+const UNIT_ANIM_DEAD: &str = "dead";
+/// state playing the clip of the action a unit performs
+const UNIT_ANIM_ATTACK: &str = "attack";
+
 impl Plugin for UnitPlugin {
     fn build(&self, app: &mut App) {
         app
@@ -36,7 +45,6 @@ impl Plugin for UnitPlugin {
                              performing_action,
                              enemy_removed,
                              health_system,
-                             unit_anim_event,
                              unit_die,
                              debug_system,
                          ).run_if(in_state(Playing)),
@@ -48,13 +56,16 @@ impl Plugin for UnitPlugin {
                 unit_attack_enemy,
                 find_enemy,
                 enemy_added,
-                action_anim_event,
-            ).run_if(in_state(Playing)))
+            ).after(Cocos2dAnimSet::AdjustSprite).run_if(in_state(Playing)))
             .add_systems(PreUpdate,
                          (
                              unit_search_prepare_sys,
                          ).run_if(in_state(Playing)),
             )
+            .observe(unit_anim_event)
+            .observe(action_anim_event::<UnitTeamLeft>)
+            .observe(action_anim_event::<UnitTeamRight>)
+            .observe(action_anim_end)
         ;
     }
 }
@@ -223,21 +234,15 @@ const MIN_DISTANCE_DIFF: f32 = 0.1;
 
 fn unit_no_attack_sys<T: Component>(
     time: Res<Time>,
-    mut query: Query<(&mut UnitMove, &mut UnitState, &UnitIntent, &mut Transform, &mut Cocos2dAnimator, &Cocos2dAnimatorPlayer), (With<T>, Without<Enemy>)>,
+    mut query: Query<(&mut UnitMove, &mut UnitState, &UnitIntent, &mut Transform, &mut Cocos2dAnimator, &mut AnimStateMachine), (With<T>, Without<Enemy>)>,
 ) {
-    for (mut unit_move, mut state, intent, mut transform, mut animator, anim_player) in query.iter_mut() {
+    for (mut unit_move, mut state, intent, mut transform, mut animator, mut anim_state) in query.iter_mut() {
         match *state {
             UnitState::Idle => {
-                if anim_player.anim_name != UnitAnimName::Stand.as_str() {
-                    animator.new_anim = Some(UnitAnimName::Stand.into());
-                    animator.mode = AnimationMode::Loop;
-                }
+                anim_state.set(UNIT_ANIM_MOVING, false);
             }
             UnitState::Moving => {
-                if anim_player.anim_name != UnitAnimName::Run.as_str() {
-                    animator.new_anim = Some(UnitAnimName::Run.into());
-                    animator.mode = AnimationMode::Loop;
-                }
+                anim_state.set(UNIT_ANIM_MOVING, true);
 
                 let mut face_dir = None;
 
@@ -273,16 +278,13 @@ fn unit_no_attack_sys<T: Component>(
 fn unit_attack_enemy<T: Component>(
     time: Res<Time>,
     mut commands: Commands,
-    mut query: Query<(Entity, &Unit, &mut UnitMove, &mut UnitState, &mut Enemy, &mut Transform, &mut Cocos2dAnimator, &Cocos2dAnimatorPlayer), (With<T>, Without<PerformingAction>)>,
+    mut query: Query<(Entity, &Unit, &mut UnitMove, &mut UnitState, &mut Enemy, &mut Transform, &mut Cocos2dAnimator, &mut AnimStateMachine), (With<T>, Without<PerformingAction>)>,
     enemy_query: Query<(&Unit, &Transform), (Without<T>, With<UnitState>)>,
 ) {
-    for (entity, unit, mut unit_move, mut state, mut enemy, mut transform, mut animator, anim_player) in query.iter_mut() {
+    for (entity, unit, mut unit_move, mut state, mut enemy, mut transform, mut animator, mut anim_state) in query.iter_mut() {
         match *state {
             UnitState::Moving => {
-                if anim_player.anim_name != UnitAnimName::Run.as_str() {
-                    animator.new_anim = Some(UnitAnimName::Run.into());
-                    animator.mode = AnimationMode::Loop;
-                }
+                anim_state.set(UNIT_ANIM_MOVING, true);
 
                 let Enemy { target, attack_range } = *enemy;
                 let (enemy_unit, enemy_transform) = if let Ok(target) = enemy_query.get(target) {
@@ -313,6 +315,9 @@ fn unit_attack_enemy<T: Component>(
                 }
             }
             UnitState::Attacking => {
+                // stand between the attacks
+                anim_state.set(UNIT_ANIM_MOVING, false);
+
                 if let Ok((enemy_unit, enemy_transform)) = enemy_query.get(enemy.target) {
                     let distance = transform.translation.truncate().distance(enemy_transform.translation.truncate());
                     let mut action = None;
@@ -340,10 +345,8 @@ fn unit_attack_enemy<T: Component>(
 
                     // find some action that satisfy the distance
                     if let Some((idx, name, act)) = action {
-                        if anim_player.anim_name != name.as_str() {
-                            animator.new_anim = Some(name.clone().into());
-                            animator.mode = AnimationMode::Once;
-                        }
+                        anim_state.set_clip(UNIT_ANIM_ATTACK, name);
+                        anim_state.set(UNIT_ANIM_ATTACKING, true);
                         commands.entity(entity).try_insert(PerformingAction {
                             idx,
                             name: name.clone(),
@@ -384,14 +387,11 @@ struct PerformingAction {
 
 fn performing_action(
     time: Res<Time>,
-    mut query: Query<(&mut Unit, &PerformingAction, &mut Cocos2dAnimator), (Added<PerformingAction>, Without<UnitDead>)>,
+    mut query: Query<(&mut Unit, &PerformingAction), (Added<PerformingAction>, Without<UnitDead>)>,
 ) {
-    for (mut unit, action, mut animator) in query.iter_mut() {
-        let (name, ref mut action) = &mut unit.actions[action.idx];
+    for (mut unit, action) in query.iter_mut() {
+        let (_, ref mut action) = &mut unit.actions[action.idx];
         action.last_use_time = time.elapsed();
-        animator.new_anim = Some(name.clone());
-        animator.mode = AnimationMode::Once;
-        animator.event_channel = Some(AnimChannel::UnitAction.into());
     }
 }
 
@@ -405,167 +405,176 @@ pub fn get_unit_aim_body_offset(unit: &Unit) -> Vec2 {
 }
 
 fn action_anim_event<T: Component + UnitTeam>(
+    trigger: Trigger<OnAnimEvent>,
     time: Res<Time>,
     mut commands: Commands,
-    mut events: EventReader<AnimEvent>,
     mut damage_event: EventWriter<DamageEvent>,
     unit_search_map: Res<UnitSearchMap>,
     query: Query<(&Unit, &UnitMove, &UnitDamage, &Transform, &PerformingAction, &Enemy), With<T>>,
     enemy_query: Query<(&Unit, &UnitMove, &Transform), Without<T>>,
 ) {
+    let EventType::Custom(frame_evt) = &trigger.event().evt_type else {
+        return;
+    };
+    let entity = trigger.entity();
+    let Ok((unit, unit_move, unit_damage, transform, pa, enemy)) = query.get(entity) else {
+        return;
+    };
     let mut rng = rand::thread_rng();
 
-    for AnimEvent { entity, channel, evt_type } in events.read() {
-        let channel = AnimChannel::from(*channel);
-        if !matches!(channel, AnimChannel::UnitAction) {
-            continue;
-        }
+    let (name, action) = &unit.actions[pa.idx];
+    // info!("action event: {:?}, {:?}, {:?}", entity, frame_evt, action.action_type);
 
-        match evt_type {
-            EventType::Custom(frame_evt) => {
-                if let Ok((unit, unit_move, unit_damage, transform, pa, enemy)) = query.get(entity.clone()) {
-                    let (name, action) = &unit.actions[pa.idx];
-                    // info!("action event: {:?}, {:?}, {:?}", entity, frame_evt, action.action_type);
-
-                    match action.action_type {
-                        ActionType::Melee(ref act) => {
-                            if !matches!(frame_evt, FrameEvent::Perform) {
-                                warn!("action {} not support frame event {:?}", name, frame_evt);
-                                continue;
-                            }
-                            let pos = match act.damage_center {
-                                MeleeDamageCenterType::Target => {
-                                    T::enemy_units(&unit_search_map).iter().find(|(e, _)| {
-                                        *e == enemy.target
-                                    }).unwrap().1.clone()
-                                }
-                                MeleeDamageCenterType::Src => {
-                                    transform.translation.truncate()
-                                }
-                            };
-
-                            let mut enemies = if act.damage_radius > 1e-1 {
-                                vec![enemy.target]
-                            } else {
-                                let mut enemies = T::enemies_in_range(&action.range,
-                                                                      &pos,
-                                                                      T::enemy_units(&unit_search_map),
-                                                                      100);
-                                if !enemies.contains(&enemy.target) {
-                                    enemies.push(enemy.target);
-                                }
-                                enemies
-                            };
-
-
-                            // info!("send damage event to enemies: {:?}", enemies);
-
-                            damage_event.send_batch(
-                                enemies.iter()
-                                    .map(|e| {
-                                        DamageEvent {
-                                            src: entity.clone(),
-                                            target: *e,
-                                            damage: random_unit_damage(unit_damage, &mut rng),
-                                        }
-                                    })
-                            );
-                        }
-                        ActionType::Projectile(ref act) => {
-                            let perform_at = match frame_evt {
-                                FrameEvent::PerformAt(pos) => {
-                                    pos.clone()
-                                }
-                                _ => {
-                                    warn!("action {} not support frame event {:?}", name, frame_evt);
-                                    continue;
-                                }
-                            };
-
-                            let (enemy_unit, enemy_move, enemy_transform) = if let Ok(enemy) = enemy_query.get(enemy.target) {
-                                enemy
-                            } else {
-                                continue;
-                            };
-                            let enemy_pos = enemy_transform.translation.truncate();
-
-                            let perform_at = if enemy_pos.x > transform.translation.x {
-                                perform_at
-                            } else {
-                                vec2(-perform_at.x, perform_at.y)
-                            };
-
-                            let spawn_pos = transform.translation.truncate() + perform_at;
-
-                            let fly_duration = enemy_pos.distance(spawn_pos) / (act.fly_speed + enemy_move.dir.length());
-
-                            let enemy_pos = enemy_pos + enemy_move.dir * fly_duration;
-                            let rand_radius = vec2(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * enemy_unit.body_radius * 0.3;
-                            let enemy_pos = enemy_pos + get_unit_aim_body_offset(enemy_unit) + rand_radius;
-
-                            // info!("enemy move: {:?}", enemy_move);
-                            // info!("act: {:?}, fly_duration: {}, spawn_pos: {:?}, enemy_pos: {:?}", act, fly_duration, spawn_pos, enemy_pos);
-
-                            let height = act.bullet_height_base + act.bullet_height_factor * spawn_pos.distance(enemy_pos);
-
-                            let gravity = height * 8.0 / (fly_duration * fly_duration);
-                            let init_velocity = Vec2 {
-                                x: (enemy_pos.x - spawn_pos.x) / fly_duration,
-                                y: (enemy_pos.y - spawn_pos.y + 0.5 * gravity * fly_duration * fly_duration) / fly_duration,
-                            };
-
-                            let acc = Vec2 {
-                                x: 0.,
-                                y: -gravity,
-                            };
-
-                            commands.spawn((
-                                OrderElement {
-                                    offset: Some(1000.),
-                                },
-                                ProjectileFly,
-                                Projectile {
-                                    damage: act.base_damage + act.damage_factor * random_unit_damage(unit_damage, &mut rng),
-                                    target: TargetType::Unit(enemy.target),
-                                    src: entity.clone(),
-                                    tolerance: enemy_unit.body_radius * 0.5,
-                                },
-                                ProjectileToFixedTarget {
-                                    acceleration: acc,
-                                    init_velocity,
-                                    dest_pos: enemy_pos,
-                                    src_pos: spawn_pos,
-                                    start_time: time.elapsed_seconds(),
-                                    fly_duration,
-                                },
-                                SpatialBundle {
-                                    transform: Transform {
-                                        translation: spawn_pos.extend(spawn_pos.y + 1000.0),
-                                        rotation: Quat::from_rotation_z(init_velocity.y.atan2(init_velocity.x)),
-                                        ..default()
-                                    },
-                                    ..default()
-                                },
-                                Cocos2dAnimator {
-                                    duration: None,
-                                    anim_handle: act.bullet_animation_handle.clone(),
-                                    mode: AnimationMode::Loop,
-                                    face_dir: AnimationFaceDir::Right,
-                                    new_anim: Some("fly".to_string()),
-                                    event_channel: Some(AnimChannel::Projectile.into()),
-                                }
-                            ));
+    match action.action_type {
+        ActionType::Melee(ref act) => {
+            if !matches!(frame_evt, FrameEvent::Perform) {
+                warn!("action {} not support frame event {:?}", name, frame_evt);
+                return;
+            }
+            let pos = match act.damage_center {
+                MeleeDamageCenterType::Target => {
+                    T::enemy_units(&unit_search_map).iter().find(|(e, _)| {
+                        *e == enemy.target
+                    }).unwrap().1.clone()
+                }
+                MeleeDamageCenterType::Src => {
+                    transform.translation.truncate()
+                }
+            };
+
+            let mut enemies = if act.damage_radius > 1e-1 {
+                vec![enemy.target]
+            } else {
+                let mut enemies = T::enemies_in_range(&action.range,
+                                                      &pos,
+                                                      T::enemy_units(&unit_search_map),
+                                                      100);
+                if !enemies.contains(&enemy.target) {
+                    enemies.push(enemy.target);
+                }
+                enemies
+            };
+
+
+            // info!("send damage event to enemies: {:?}", enemies);
+
+            damage_event.send_batch(
+                enemies.iter()
+                    .map(|e| {
+                        DamageEvent {
+                            src: entity,
+                            target: *e,
+                            damage: random_unit_damage(unit_damage, &mut rng),
                         }
-                    }
+                    })
+            );
+        }
+        ActionType::Projectile(ref act) => {
+            let perform_at = match frame_evt {
+                FrameEvent::PerformAt(pos) => {
+                    pos.clone()
                 }
-            }
-            EventType::End => {
-                commands.entity(entity.clone()).remove::<PerformingAction>();
-            }
+                _ => {
+                    warn!("action {} not support frame event {:?}", name, frame_evt);
+                    return;
+                }
+            };
+
+            let (enemy_unit, enemy_move, enemy_transform) = if let Ok(enemy) = enemy_query.get(enemy.target) {
+                enemy
+            } else {
+                return;
+            };
+            let enemy_pos = enemy_transform.translation.truncate();
+
+            let perform_at = if enemy_pos.x > transform.translation.x {
+                perform_at
+            } else {
+                vec2(-perform_at.x, perform_at.y)
+            };
+
+            let spawn_pos = transform.translation.truncate() + perform_at;
+
+            let fly_duration = enemy_pos.distance(spawn_pos) / (act.fly_speed + enemy_move.dir.length());
+
+            let enemy_pos = enemy_pos + enemy_move.dir * fly_duration;
+            let rand_radius = vec2(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * enemy_unit.body_radius * 0.3;
+            let enemy_pos = enemy_pos + get_unit_aim_body_offset(enemy_unit) + rand_radius;
+
+            // info!("enemy move: {:?}", enemy_move);
+            // info!("act: {:?}, fly_duration: {}, spawn_pos: {:?}, enemy_pos: {:?}", act, fly_duration, spawn_pos, enemy_pos);
+
+            let height = act.bullet_height_base + act.bullet_height_factor * spawn_pos.distance(enemy_pos);
+
+            let gravity = height * 8.0 / (fly_duration * fly_duration);
+            let init_velocity = Vec2 {
+                x: (enemy_pos.x - spawn_pos.x) / fly_duration,
+                y: (enemy_pos.y - spawn_pos.y + 0.5 * gravity * fly_duration * fly_duration) / fly_duration,
+            };
+
+            let acc = Vec2 {
+                x: 0.,
+                y: -gravity,
+            };
+
+            commands.spawn((
+                OrderElement {
+                    offset: Some(1000.),
+                },
+                ProjectileFly,
+                Projectile {
+                    damage: act.base_damage + act.damage_factor * random_unit_damage(unit_damage, &mut rng),
+                    target: TargetType::Unit(enemy.target),
+                    src: entity,
+                    tolerance: enemy_unit.body_radius * 0.5,
+                },
+                ProjectileToFixedTarget {
+                    acceleration: acc,
+                    init_velocity,
+                    dest_pos: enemy_pos,
+                    src_pos: spawn_pos,
+                    start_time: time.elapsed_seconds(),
+                    fly_duration,
+                },
+                SpatialBundle {
+                    transform: Transform {
+                        translation: spawn_pos.extend(spawn_pos.y + 1000.0),
+                        rotation: Quat::from_rotation_z(init_velocity.y.atan2(init_velocity.x)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                Cocos2dAnimator {
+                    anim_handle: act.bullet_animation_handle.clone(),
+                    face_dir: AnimationFaceDir::Right,
+                    ..default()
+                },
+                AnimStateMachine::new(act.bullet_anim_state_handle.clone()),
+            ));
         }
     }
 }
 
+/// The action's clip played through, the next one can be picked.
+fn action_anim_end(
+    trigger: Trigger<OnAnimEvent>,
+    mut commands: Commands,
+    mut query: Query<(&PerformingAction, &Cocos2dAnimatorPlayer, &mut AnimStateMachine)>,
+) {
+    if !matches!(trigger.event().evt_type, EventType::End) {
+        return;
+    }
+    let entity = trigger.entity();
+    let Ok((action, anim_player, mut anim_state)) = query.get_mut(entity) else {
+        return;
+    };
+
+    if anim_player.anim_name == action.name {
+        commands.entity(entity).remove::<PerformingAction>();
+        anim_state.set(UNIT_ANIM_ATTACKING, false);
+    }
+}
+
 pub enum UnitAttackType {
     Melee,
     Magic,
@@ -848,6 +857,8 @@ pub struct UnitMove {
 pub struct UnitBundle {
     pub unit: Unit,
     pub state: UnitState,
+    /// plays born, stand, run, the attacks and die, see [`UNIT_ANIM_STATE_MACHINE`]
+    pub anim_state: AnimStateMachine,
     pub health: UnitHealth,
     pub damage: UnitDamage,
     pub intent: UnitIntent,
@@ -883,6 +894,7 @@ impl UnitBundle {
                 body_height: unit_info.body_height,
             },
             state: UnitState::Idle,
+            anim_state: AnimStateMachine::new(asset_server.load(UNIT_ANIM_STATE_MACHINE)),
             health: UnitHealth {
                 health: unit_info.health_base + unit_info.health_factor * level as f32,
                 health_recovery_speed: unit_info.health_recovery_speed,
@@ -902,45 +914,6 @@ impl UnitBundle {
     }
 }
 
-pub enum UnitAnimName {
-    Born,
-    Stand,
-    Run,
-    Die,
-    Action(String),
-}
-
-impl From<String> for UnitAnimName {
-    fn from(value: String) -> Self {
-        match value.as_str() {
-            "born" => UnitAnimName::Born,
-            "stand" => UnitAnimName::Stand,
-            "run" => UnitAnimName::Run,
-            "die" => UnitAnimName::Die,
-            _ => UnitAnimName::Action(value),
-        }
-    }
-}
-
-impl From<UnitAnimName> for String {
-    fn from(value: UnitAnimName) -> Self {
-        value.as_str().to_string()
-    }
-}
-
-impl UnitAnimName {
-    pub fn as_str(&self) -> &str {
-        match self {
-            UnitAnimName::Born => "born",
-            UnitAnimName::Stand => "stand",
-            UnitAnimName::Run => "run",
-            UnitAnimName::Die => "die",
-            UnitAnimName::Action(s) => s.as_str(),
-        }
-    }
-}
-
-
 #[derive(Component)]
 pub struct Enemy {
     pub target: Entity,
@@ -1006,7 +979,7 @@ impl UnitTeam for UnitTeamLeft {
                 continue;
             }
 
-            return Some(entity.clone());
+            return Some(entity);
         }
 
         None
@@ -1028,7 +1001,7 @@ impl UnitTeam for UnitTeamLeft {
                 continue;
             }
 
-            enemies.push(entity.clone());
+            enemies.push(entity);
             if enemies.len() >= max_units {
                 break;
             }
@@ -1052,7 +1025,7 @@ impl UnitTeam for UnitTeamLeft {
                 continue;
             }
 
-            teammates.push(entity.clone());
+            teammates.push(entity);
             if teammates.len() >= max_units {
                 break;
             }
@@ -1081,7 +1054,7 @@ impl UnitTeam for UnitTeamRight {
                 continue;
             }
 
-            return Some(entity.clone());
+            return Some(entity);
         }
 
         None
@@ -1102,7 +1075,7 @@ impl UnitTeam for UnitTeamRight {
                 continue;
             }
 
-            enemies.push(entity.clone());
+            enemies.push(entity);
             if enemies.len() >= max_units {
                 break;
             }
@@ -1126,7 +1099,7 @@ impl UnitTeam for UnitTeamRight {
                 continue;
             }
 
-            teammates.push(entity.clone());
+            teammates.push(entity);
             if teammates.len() >= max_units {
                 break;
             }
@@ -1163,64 +1136,55 @@ fn health_system(
 }
 
 fn unit_die(
-    mut query: Query<&mut Cocos2dAnimator, (Added<UnitDead>, With<Unit>)>,
+    mut query: Query<&mut AnimStateMachine, (Added<UnitDead>, With<Unit>)>,
 ) {
-    for mut animator in query.iter_mut() {
-        animator.new_anim = Some(UnitAnimName::Die.into());
-        animator.mode = AnimationMode::Once;
-        animator.event_channel = Some(AnimChannel::Unit.into());
+    for mut anim_state in query.iter_mut() {
+        anim_state.set(UNIT_ANIM_DEAD, true);
     }
 }
 
 fn unit_anim_event(
+    trigger: Trigger<OnAnimEvent>,
     mut commands: Commands,
-    mut events: EventReader<AnimEvent>,
     mut query: Query<(Option<&mut UnitState>, Option<&UnitIntent>, &Cocos2dAnimatorPlayer)>,
 ) {
-    for evt in events.read() {
-        let AnimEvent { entity, channel, evt_type } = evt;
-        let channel = AnimChannel::from(*channel);
-        if !matches!(channel, AnimChannel::Unit) {
-            continue;
-        }
+    if !matches!(trigger.event().evt_type, EventType::End) {
+        return;
+    }
+    let entity = trigger.entity();
+    let Ok((state, intent, anim_player)) = query.get_mut(entity) else {
+        return;
+    };
 
-        match evt_type {
-            EventType::End => {
-                if let Ok((mut state, intent, anim_player)) = query.get_mut(entity.clone()) {
-                    match anim_player.anim_name.as_str() {
-                        "die" => {
-                            commands.entity(entity.clone()).despawn_recursive();
-                        }
-                        "born" => {
-                            let intent = if let Some(intent) = intent {
-                                intent
-                            } else {
-                                warn!("unit born but no intent");
-                                continue;
-                            };
-
-                            let mut state = if let Some(state) = state {
-                                state
-                            } else {
-                                warn!("unit born but no state");
-                                continue;
-                            };
-
-                            match *intent {
-                                UnitIntent::MoveTo(_) => {
-                                    *state = Moving;
-                                }
-                                UnitIntent::AttackTo(_) => {
-                                    *state = Moving;
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
+    match anim_player.anim_name.as_str() {
+        "die" => {
+            commands.entity(entity).despawn_animator();
+        }
+        "born" => {
+            let intent = if let Some(intent) = intent {
+                intent
+            } else {
+                warn!("unit born but no intent");
+                return;
+            };
+
+            let mut state = if let Some(state) = state {
+                state
+            } else {
+                warn!("unit born but no state");
+                return;
+            };
+
+            match *intent {
+                UnitIntent::MoveTo(_) => {
+                    *state = Moving;
+                }
+                UnitIntent::AttackTo(_) => {
+                    *state = Moving;
                 }
+                _ => {}
             }
-            _ => {}
         }
+        _ => {}
     }
 }
\ No newline at end of file
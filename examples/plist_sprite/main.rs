@@ -24,7 +24,7 @@ fn main() {
                      //     ..default()
                      // })
         )
-        .add_plugins(Cocos2dAnimPlugin)
+        .add_plugins(Cocos2dAnimPlugin::default())
         .init_resource::<AnimDataRes>()
         .add_systems(Startup, setup)
         .add_systems(Update, (
@@ -89,12 +89,12 @@ fn spawn_anim(
 ) {
     commands.spawn((
         Cocos2dAnimator {
-            duration: None,
             anim_handle: anim_data.anim.clone(),
             new_anim: Some("born".to_string()),
             mode: AnimationMode::Loop,
             event_channel: Some(0),
             face_dir: AnimationFaceDir::Right,
+            ..default()
         },
         SpatialBundle {
             transform: Transform {